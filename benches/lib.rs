@@ -17,4 +17,4 @@ fn default_config() -> Criterion {
         .measurement_time(Duration::from_secs(5))
 }
 
-criterion_main!(src::fixed_group, src::scaled_group);
+criterion_main!(src::fixed_group, src::scaled_group, src::contended_group);
@@ -1,12 +1,16 @@
 use async_resource::PoolConfig;
 use criterion::{criterion_group, BenchmarkId, Criterion};
+use crossbeam_queue::ArrayQueue;
 use futures::future::join_all;
 use std::sync::atomic::AtomicU32;
 use std::sync::atomic::Ordering::SeqCst;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
 
 static COUNT: usize = 100_000;
+static TASKS: usize = 256;
+static ROUNDS: usize = 100;
 
 pub fn tub_and_simple_pool(c: &mut Criterion) {
     let mut group = c.benchmark_group("Tub vs. Simple Pool");
@@ -89,6 +93,112 @@ pub fn fixed(c: &mut Criterion) {
     group.finish();
 }
 
+/// Many tasks acquiring and releasing over and over, so values almost always come back
+/// to a pool with tasks waiting for them
+pub fn contended(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Acquire & Release (Contended)");
+
+    for size in [1, 4, 16] {
+        group.bench_with_input(BenchmarkId::new("tub", size), &size, |b, size| {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            b.to_async(rt)
+                .iter(|| run_tub_contended(tub::Pool::from_initializer(*size, || 1)));
+        });
+
+        group.bench_with_input(BenchmarkId::new("no-hand-off", size), &size, |b, size| {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            b.to_async(rt)
+                .iter(|| run_notify_contended(Arc::new(NotifyPool::new(*size))));
+        });
+
+        group.bench_with_input(BenchmarkId::new("tub-fair", size), &size, |b, size| {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            b.to_async(rt).iter(|| {
+                let pool = tub::Pool::builder()
+                    .fair(true)
+                    .build_with_initializer(*size, || 1);
+                run_tub_contended(pool)
+            });
+        });
+
+        group.bench_with_input(
+            BenchmarkId::new("async-object-pool", size),
+            &size,
+            |b, size| {
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                b.to_async(rt)
+                    .iter(|| run_aop_contended(async_object_pool::Pool::new(*size)));
+            },
+        );
+
+        group.bench_with_input(BenchmarkId::new("simple-pool", size), &size, |b, size| {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            b.to_async(rt).iter(|| {
+                let pool = simple_pool::ResourcePool::with_capacity(*size);
+                (0..*size).for_each(|_| pool.append(1));
+                run_simple_pool_contended(pool)
+            });
+        });
+    }
+
+    group.finish();
+}
+
+/// The longest any one acquire waits in the contended benchmark. Handing returned values
+/// to waiting tasks costs some throughput, but keeps a task that returns a value from
+/// taking it straight back while others wait.
+pub fn contended_longest_wait(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Longest Wait (Contended)");
+
+    for size in [1, 4, 16] {
+        group.bench_with_input(BenchmarkId::new("tub", size), &size, |b, size| {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            b.iter_custom(|iters| {
+                (0..iters)
+                    .map(|_| {
+                        rt.block_on(longest_tub_wait(tub::Pool::from_initializer(*size, || 1)))
+                    })
+                    .sum()
+            });
+        });
+
+        group.bench_with_input(BenchmarkId::new("no-hand-off", size), &size, |b, size| {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            b.iter_custom(|iters| {
+                (0..iters)
+                    .map(|_| rt.block_on(longest_notify_wait(Arc::new(NotifyPool::new(*size)))))
+                    .sum()
+            });
+        });
+    }
+
+    group.finish();
+}
+
+/// Many tasks on every worker thread sharing a pool with a value for each of them,
+/// so the cost is in the pool's queue rather than in waiting
+pub fn sharded(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Acquire & Release (Sharded)");
+    let values = std::thread::available_parallelism().map_or(4, usize::from) * 4;
+
+    let iters = (10..18).map(|i| 2usize.pow(i as u32)).collect::<Vec<_>>();
+    for i in iters {
+        group.bench_with_input(BenchmarkId::new("tub", i), &i, |b, i| {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            b.to_async(rt)
+                .iter(|| run_tub(tub::Pool::from_initializer(values, || 1), *i));
+        });
+
+        group.bench_with_input(BenchmarkId::new("tub-sharded", i), &i, |b, i| {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            b.to_async(rt)
+                .iter(|| run_tub_sharded(tub::ShardedPool::from_vec(vec![1; values]), *i));
+        });
+    }
+
+    group.finish();
+}
+
 async fn run_tub(pool: tub::Pool<u32>, iters: usize) {
     let pool = Arc::new(pool);
     join_all(
@@ -104,6 +214,20 @@ async fn run_tub(pool: tub::Pool<u32>, iters: usize) {
     .await;
 }
 
+async fn run_tub_sharded(pool: tub::ShardedPool<u32>, iters: usize) {
+    join_all(
+        (0..iters)
+            .map(|_| {
+                let pool = pool.clone();
+                tokio::spawn(async move {
+                    let _resource = pool.acquire().await;
+                })
+            })
+            .collect::<Vec<_>>(),
+    )
+    .await;
+}
+
 async fn run_aop(pool: async_object_pool::Pool<u32>, iters: usize) {
     let pool = Arc::new(pool);
     join_all(
@@ -150,6 +274,162 @@ async fn run_async_resource_pool(pool: async_resource::Pool<u32, ()>, iters: usi
     .await;
 }
 
+async fn run_tub_contended(pool: tub::Pool<u32>) {
+    join_all(
+        (0..TASKS)
+            .map(|_| {
+                let pool = pool.clone();
+                tokio::spawn(async move {
+                    for _ in 0..ROUNDS {
+                        let _resource = pool.acquire().await;
+                        tokio::task::yield_now().await;
+                    }
+                })
+            })
+            .collect::<Vec<_>>(),
+    )
+    .await;
+}
+
+/// How tub returned values before handing them to waiting tasks: the value goes back
+/// into the queue and one waiter is woken, racing every other task for it
+struct NotifyPool {
+    queue: ArrayQueue<u32>,
+    notify: Notify,
+}
+
+impl NotifyPool {
+    fn new(size: usize) -> Self {
+        let queue = ArrayQueue::new(size);
+        for _ in 0..size {
+            let _ = queue.push(1);
+        }
+        NotifyPool {
+            queue,
+            notify: Notify::new(),
+        }
+    }
+
+    async fn acquire(&self) -> NotifyGuard<'_> {
+        loop {
+            if let Some(value) = self.queue.pop() {
+                return NotifyGuard { pool: self, value };
+            }
+            self.notify.notified().await;
+        }
+    }
+}
+
+struct NotifyGuard<'a> {
+    pool: &'a NotifyPool,
+    value: u32,
+}
+
+impl Drop for NotifyGuard<'_> {
+    fn drop(&mut self) {
+        let _ = self.pool.queue.push(self.value);
+        self.pool.notify.notify_one();
+    }
+}
+
+async fn run_notify_contended(pool: Arc<NotifyPool>) {
+    join_all(
+        (0..TASKS)
+            .map(|_| {
+                let pool = pool.clone();
+                tokio::spawn(async move {
+                    for _ in 0..ROUNDS {
+                        let _resource = pool.acquire().await;
+                        tokio::task::yield_now().await;
+                    }
+                })
+            })
+            .collect::<Vec<_>>(),
+    )
+    .await;
+}
+
+async fn longest_tub_wait(pool: tub::Pool<u32>) -> Duration {
+    let waits = join_all(
+        (0..TASKS)
+            .map(|_| {
+                let pool = pool.clone();
+                tokio::spawn(async move {
+                    let mut longest = Duration::ZERO;
+                    for _ in 0..ROUNDS {
+                        let start = Instant::now();
+                        let _resource = pool.acquire().await;
+                        longest = longest.max(start.elapsed());
+                        tokio::task::yield_now().await;
+                    }
+                    longest
+                })
+            })
+            .collect::<Vec<_>>(),
+    )
+    .await;
+    waits.into_iter().map(Result::unwrap).max().unwrap()
+}
+
+async fn longest_notify_wait(pool: Arc<NotifyPool>) -> Duration {
+    let waits = join_all(
+        (0..TASKS)
+            .map(|_| {
+                let pool = pool.clone();
+                tokio::spawn(async move {
+                    let mut longest = Duration::ZERO;
+                    for _ in 0..ROUNDS {
+                        let start = Instant::now();
+                        let _resource = pool.acquire().await;
+                        longest = longest.max(start.elapsed());
+                        tokio::task::yield_now().await;
+                    }
+                    longest
+                })
+            })
+            .collect::<Vec<_>>(),
+    )
+    .await;
+    waits.into_iter().map(Result::unwrap).max().unwrap()
+}
+
+async fn run_aop_contended(pool: async_object_pool::Pool<u32>) {
+    let pool = Arc::new(pool);
+    join_all(
+        (0..TASKS)
+            .map(|_| {
+                let pool = pool.clone();
+                tokio::spawn(async move {
+                    for _ in 0..ROUNDS {
+                        let resource = pool.take_or_create(|| 1).await;
+                        tokio::task::yield_now().await;
+                        pool.put(resource).await;
+                    }
+                })
+            })
+            .collect::<Vec<_>>(),
+    )
+    .await;
+}
+
+async fn run_simple_pool_contended(pool: simple_pool::ResourcePool<u32>) {
+    let pool = Arc::new(pool);
+    join_all(
+        (0..TASKS)
+            .map(|_| {
+                let pool = pool.clone();
+                tokio::spawn(async move {
+                    for _ in 0..ROUNDS {
+                        let _resource = pool.get().await;
+                        tokio::task::yield_now().await;
+                    }
+                })
+            })
+            .collect::<Vec<_>>(),
+    )
+    .await;
+}
+
 fn create_async_resource_pool() -> async_resource::Pool<u32, ()> {
     fn counter_pool_config() -> PoolConfig<u32, ()> {
         let source = Arc::new(AtomicU32::new(0));
@@ -181,5 +461,13 @@ criterion_group!(
     .sample_size(25)
     .warm_up_time(Duration::from_millis(10))
     .measurement_time(Duration::from_millis(25));
-    targets = scaled, tub_and_simple_pool
+    targets = scaled, tub_and_simple_pool, sharded
+);
+
+criterion_group!(
+    name = contended_group;
+    config = crate::default_config()
+    .sample_size(20)
+    .measurement_time(Duration::from_secs(10));
+    targets = contended, contended_longest_wait
 );
@@ -0,0 +1,408 @@
+use crate::trace::WaitSpan;
+use crate::{AcquireError, Entry, Guard, InLine, Origin, Pool, PoolInner, Priority, Waiting};
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::{AcqRel, Release};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{option, vec};
+
+/// A handle to values acquired together with [`Pool::acquire_many`]
+///
+/// The values are read and written as a slice. When the [`GuardSet`] is dropped, every
+/// value is returned to the pool.
+///
+/// # Examples
+///
+/// ```
+/// use tub::{GuardSet, Pool};
+///
+/// #[tokio::main]
+/// async fn main() {
+///   let pool = Pool::from_vec(vec![0; 4]);
+///
+///   // Get three values at once
+///   let mut values: GuardSet<u32> = pool.acquire_many(3).await.unwrap();
+///   values.fill(7);
+///   assert_eq!(*values, [7, 7, 7]);
+///
+///   // Return them to the pool
+///   drop(values);
+///   assert_eq!(pool.remaining_capacity(), 4);
+/// }
+/// ```
+pub struct GuardSet<T> {
+    values: Few<T>,
+    origins: Few<Origin>,
+    /// When the values were handed out, if anything needs to know how long they are held
+    acquired: Option<Instant>,
+    /// A reference to the pool used to return the values when dropped
+    inner: Arc<PoolInner<T>>,
+}
+
+impl<T> Pool<T> {
+    /// Wait until `n` values are available, without a deadline.
+    ///
+    /// This is the wait loop behind every acquire, including [`Pool::acquire`] as a set
+    /// of one value.
+    ///
+    /// Waits sharing a `claim` take turns reserving room for new values, so that only one
+    /// of them creates values at a time. They aren't counted as waiters, since the caller
+    /// sharing the claim counts itself once.
+    pub(crate) async fn wait_many(
+        &self,
+        n: usize,
+        priority: Priority,
+        claim: Option<&AtomicBool>,
+    ) -> Result<GuardSet<T>, AcquireError> {
+        let inner = &*self.inner;
+        // Set once the caller has to wait, so that the clock isn't read before then
+        let mut start: Option<Instant> = None;
+        let mut span = WaitSpan::default();
+        // Our place in line, once we have had to wait
+        let mut line: Option<InLine<'_, T>> = None;
+        // How many values were handed to us when we were last woken
+        let mut handed = 0;
+        let values = loop {
+            if inner.is_closed() {
+                return Err(AcquireError::Closed);
+            }
+
+            // Values handed to us are ours to keep, even when it isn't our turn
+            let may_take = match &line {
+                Some(line) => handed == n || line.may_take(),
+                None => inner.waiters.may_skip_line(),
+            };
+
+            if may_take {
+                if let Some(mut batch) = Batch::gather(inner, n - handed, claim) {
+                    // Everything we need is ours, so the next in line can go ahead
+                    if let Some(line) = line.take() {
+                        let mut entries = line.finish();
+                        // Values handed over since we were woken are more than we need
+                        for entry in entries.drain(handed.min(entries.len())..) {
+                            inner.push(entry);
+                        }
+                        batch.entries.extend(entries);
+                    }
+                    if !batch.fill().await? {
+                        continue;
+                    }
+                    if inner.is_closed() {
+                        return Err(AcquireError::Closed);
+                    }
+                    break batch.finish();
+                }
+            }
+
+            if inner.is_exhausted(n) {
+                return Err(AcquireError::Exhausted);
+            }
+
+            match &line {
+                // Values that came back before we joined didn't wake anyone for us,
+                // so look at the pool again before waiting
+                None => {
+                    let now = Instant::now();
+                    start = Some(now);
+                    line = Some(InLine::join(inner, priority, n, now));
+                }
+                Some(line) => {
+                    let _waiting = claim.is_none().then(|| Waiting::new(inner));
+                    handed = span.instrument(inner.name(), line.wait()).await;
+                }
+            }
+        };
+        drop(line);
+
+        let waited = start.map_or(Duration::ZERO, |start| start.elapsed());
+        if inner.config.record_timings {
+            inner.wait_time.record(waited);
+        }
+
+        #[cfg(feature = "tracing")]
+        if let Some(threshold) = inner.config.slow_acquire_threshold {
+            if waited > threshold {
+                let pool = inner.name();
+                tracing::warn!(pool, ?waited, "slow acquire from the pool");
+            }
+        }
+
+        let mut set = GuardSet {
+            values: Few::with_capacity(n),
+            origins: Few::with_capacity(n),
+            acquired: inner.hold_start(),
+            inner: self.inner.clone(),
+        };
+        for (value, origin) in values {
+            inner.begin_use(&value, origin.id, waited);
+            set.values.push(value);
+            set.origins.push(origin);
+        }
+        Ok(set)
+    }
+}
+
+impl<T> GuardSet<T> {
+    /// Turn a set of a single value into a [`Guard`]
+    pub(crate) fn into_guard(mut self) -> Guard<T> {
+        // Safety: Only called on sets of one value
+        let value = self.values.pop().unwrap();
+        let origin = self.origins.pop().unwrap();
+        Guard {
+            value: Some(value),
+            origin,
+            acquired: self.acquired,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// Values gathered for [`Pool::acquire_many`] that haven't been handed out yet
+///
+/// If the [`Batch`] is dropped, e.g. because the set couldn't be completed or the
+/// future gathering it was cancelled, its values go back to the pool and its room
+/// is given back.
+struct Batch<'a, T> {
+    inner: &'a PoolInner<T>,
+    /// Idle values that still have to be prepared
+    entries: Few<Entry<T>>,
+    /// Values ready to be handed out
+    ready: Few<(T, Origin)>,
+    /// Room reserved for values that still have to be created
+    reserved: usize,
+    /// Shared with other waits, see [`Pool::wait_many`]
+    claim: Option<&'a AtomicBool>,
+    /// Whether this batch holds the claim
+    claimed: bool,
+}
+
+impl<'a, T> Batch<'a, T> {
+    /// Take `n` idle values, or room to create the ones that aren't idle.
+    ///
+    /// Returns [`None`] if there isn't enough of either, giving back everything taken.
+    fn gather(inner: &'a PoolInner<T>, n: usize, claim: Option<&'a AtomicBool>) -> Option<Self> {
+        let mut batch = Batch {
+            inner,
+            entries: Few::with_capacity(n),
+            ready: Few::with_capacity(n),
+            reserved: 0,
+            claim,
+            claimed: false,
+        };
+
+        while batch.entries.len() < n {
+            match inner.pop() {
+                Some(entry) => batch.entries.push(entry),
+                None => break,
+            }
+        }
+
+        let missing = n - batch.entries.len();
+        if missing > 0 && !batch.reserve(missing) {
+            return None;
+        }
+        batch.reserved = missing;
+        Some(batch)
+    }
+
+    /// Reserve room for `n` values, once no other wait sharing the claim holds it
+    fn reserve(&mut self, n: usize) -> bool {
+        if let Some(claim) = self.claim {
+            if !self.claimed && claim.swap(true, AcqRel) {
+                return false;
+            }
+            self.claimed = true;
+        }
+        self.inner.try_reserve_many(n)
+    }
+
+    /// Prepare every idle value and create the rest.
+    ///
+    /// Returns `false` if a value failed its checks and there was no room to replace it.
+    async fn fill(&mut self) -> Result<bool, AcquireError> {
+        while let Some(entry) = self.entries.pop() {
+            let origin = entry.origin;
+            match self.inner.prepare(entry).await {
+                Some(value) => self.ready.push((value, origin)),
+                // The failed value made room for a replacement, unless someone beat us to it
+                None if self.reserve(1) => self.reserved += 1,
+                None => return Ok(false),
+            }
+        }
+
+        while self.reserved > 0 {
+            // The room now belongs to `create`, which gives it back if it fails
+            self.reserved -= 1;
+            let created = self.inner.create().await?;
+            self.ready.push(created);
+        }
+        Ok(true)
+    }
+
+    /// Take the values, once every one of them is ready
+    fn finish(mut self) -> Few<(T, Origin)> {
+        mem::take(&mut self.ready)
+    }
+}
+
+impl<T> Drop for Batch<'_, T> {
+    fn drop(&mut self) {
+        for entry in mem::take(&mut self.entries) {
+            self.inner.push(entry);
+        }
+
+        let ready = mem::take(&mut self.ready);
+        let now = (ready.len() > 0).then(Instant::now);
+        for (value, origin) in ready {
+            self.inner.push(Entry {
+                value,
+                origin,
+                returned: now.unwrap_or(origin.created),
+                unchecked: false,
+            });
+        }
+
+        for _ in 0..self.reserved {
+            self.inner.release();
+        }
+
+        // Let the next wait sharing the claim create values
+        if let (Some(claim), true) = (self.claim, self.claimed) {
+            claim.store(false, Release);
+        }
+    }
+}
+
+impl<T> Drop for GuardSet<T> {
+    /// # Examples
+    ///
+    /// ```
+    /// use tub::Pool;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///   let pool: Pool<u32> = Pool::from_default(2);
+    ///   let values = pool.acquire_many(2).await.unwrap();
+    ///   assert!(pool.try_acquire().is_none());
+    ///
+    ///   // Return both values to the pool
+    ///   drop(values);
+    ///   assert_eq!(pool.status().idle, 2);
+    /// }
+    /// ```
+    fn drop(&mut self) {
+        let values = mem::take(&mut self.values);
+        for (value, origin) in values.into_iter().zip(self.origins.as_slice()) {
+            self.inner.end_use(&value, origin.id, self.acquired);
+            self.inner.put(value, *origin);
+        }
+    }
+}
+
+impl<T> Deref for GuardSet<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        self.values.as_slice()
+    }
+}
+
+impl<T> DerefMut for GuardSet<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.values.as_mut_slice()
+    }
+}
+
+/// A list that keeps a single value inline, so that acquiring one value doesn't
+/// allocate
+enum Few<V> {
+    One(Option<V>),
+    Many(Vec<V>),
+}
+
+impl<V> Few<V> {
+    fn with_capacity(n: usize) -> Self {
+        match n {
+            0 | 1 => Few::One(None),
+            n => Few::Many(Vec::with_capacity(n)),
+        }
+    }
+
+    fn push(&mut self, value: V) {
+        match self {
+            Few::One(slot @ None) => *slot = Some(value),
+            Few::One(slot) => *self = Few::Many(slot.take().into_iter().chain([value]).collect()),
+            Few::Many(values) => values.push(value),
+        }
+    }
+
+    fn pop(&mut self) -> Option<V> {
+        match self {
+            Few::One(slot) => slot.take(),
+            Few::Many(values) => values.pop(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    fn as_slice(&self) -> &[V] {
+        match self {
+            Few::One(slot) => slot.as_slice(),
+            Few::Many(values) => values,
+        }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [V] {
+        match self {
+            Few::One(slot) => slot.as_mut_slice(),
+            Few::Many(values) => values,
+        }
+    }
+}
+
+impl<V> Default for Few<V> {
+    fn default() -> Self {
+        Few::One(None)
+    }
+}
+
+impl<V> Extend<V> for Few<V> {
+    fn extend<I: IntoIterator<Item = V>>(&mut self, values: I) {
+        for value in values {
+            self.push(value);
+        }
+    }
+}
+
+impl<V> IntoIterator for Few<V> {
+    type Item = V;
+    type IntoIter = FewIter<V>;
+
+    fn into_iter(self) -> FewIter<V> {
+        match self {
+            Few::One(slot) => FewIter::One(slot.into_iter()),
+            Few::Many(values) => FewIter::Many(values.into_iter()),
+        }
+    }
+}
+
+/// The values of a [`Few`], in the order they were pushed
+enum FewIter<V> {
+    One(option::IntoIter<V>),
+    Many(vec::IntoIter<V>),
+}
+
+impl<V> Iterator for FewIter<V> {
+    type Item = V;
+
+    fn next(&mut self) -> Option<V> {
+        match self {
+            FewIter::One(values) => values.next(),
+            FewIter::Many(values) => values.next(),
+        }
+    }
+}
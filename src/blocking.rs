@@ -0,0 +1,119 @@
+use crate::{AcquireError, Guard, Pool, Priority};
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+impl<T> Pool<T> {
+    /// Acquire a value from the pool like [`Pool::acquire`], parking the calling thread
+    /// until one is available.
+    ///
+    /// This is meant for threads that can't `.await`, such as `std::thread` workers or
+    /// FFI callbacks. The thread waits in the same line as the tasks calling
+    /// [`Pool::acquire`], so values are shared fairly between both. If the pool was built
+    /// with a `Builder::wait_timeout`, waiting longer than that fails with
+    /// [`AcquireError::Timeout`].
+    ///
+    /// Don't call this from an async task, since it blocks the executor's thread. Values
+    /// created by a [`Manager`](crate::Manager) are created on the calling thread, so the
+    /// manager must not rely on being run inside an async runtime.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`Pool::acquire`].
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    ///
+    /// let pool: Pool<u32> = Pool::from_default(1);
+    /// let value = pool.acquire_blocking().unwrap();
+    ///
+    /// let waiter = std::thread::spawn({
+    ///     let pool = pool.clone();
+    ///     move || *pool.acquire_blocking().unwrap()
+    /// });
+    ///
+    /// // Returning the value wakes the waiting thread
+    /// drop(value);
+    /// assert_eq!(waiter.join().unwrap(), 0);
+    /// ```
+    pub fn acquire_blocking(&self) -> Result<Guard<T>, AcquireError> {
+        #[cfg(feature = "time")]
+        if let Some(timeout) = self.inner.config.wait_timeout {
+            return self.acquire_blocking_timeout(timeout);
+        }
+
+        self.acquire_blocking_until(None)
+    }
+
+    /// Acquire a value from the pool like [`Pool::acquire_blocking`], parking the calling
+    /// thread for at most `timeout`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AcquireError::Timeout`] if no value became available in time.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use tub::{AcquireError, Pool};
+    ///
+    /// let pool: Pool<u32> = Pool::from_default(1);
+    /// let value = pool.acquire_blocking_timeout(Duration::from_secs(1)).unwrap();
+    ///
+    /// let result = pool.acquire_blocking_timeout(Duration::from_millis(10));
+    /// assert!(matches!(result, Err(AcquireError::Timeout)));
+    /// ```
+    pub fn acquire_blocking_timeout(&self, timeout: Duration) -> Result<Guard<T>, AcquireError> {
+        self.acquire_blocking_until(Some(Instant::now() + timeout))
+    }
+
+    fn acquire_blocking_until(&self, deadline: Option<Instant>) -> Result<Guard<T>, AcquireError> {
+        match block_on(self.wait(Priority::Normal), deadline) {
+            Some(result) => result,
+            None => Err(self.inner.timed_out()),
+        }
+    }
+}
+
+/// Wakes a thread parked in [`block_on`]
+struct Unpark(Thread);
+
+impl Wake for Unpark {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Run `future` on the calling thread, parking it whenever the future is pending.
+///
+/// Returns [`None`] if the future isn't done by `deadline`, after dropping it.
+fn block_on<F: Future>(future: F, deadline: Option<Instant>) -> Option<F::Output> {
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return Some(output);
+        }
+
+        // Wakeups can be spurious, so the future is simply polled again
+        match deadline {
+            None => thread::park(),
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    return None;
+                }
+                thread::park_timeout(deadline - now);
+            }
+        }
+    }
+}
@@ -0,0 +1,838 @@
+use crate::check::Check;
+use crate::hooks::{Hooks, Metadata};
+use crate::manager::{BoxFuture, Source};
+use crate::{KeyedPool, Manager, Pool, QueueMode, Reset, ShardedPool};
+use std::hash::Hash;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A builder for a [`Pool`] with custom settings
+///
+/// Use [`Pool::builder`] to create one, chain the settings you need, and
+/// finish with one of the `build` methods.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use tub::Pool;
+///
+/// let pool: Pool<u32> = Pool::builder()
+///     .idle_timeout(Duration::from_secs(60))
+///     .build(0..10);
+/// assert_eq!(pool.remaining_capacity(), 10);
+/// ```
+pub struct Builder<T> {
+    config: Config<T>,
+}
+
+type ResetFn<T> = dyn Fn(&mut T) + Send + Sync;
+
+/// Settings shared by every handle to a pool
+pub(crate) struct Config<T> {
+    /// Tells the pool apart from others, see [`Builder::name`]
+    pub(crate) name: Option<String>,
+    /// How long [`Pool::acquire`] waits for a value, if bounded
+    #[cfg(feature = "time")]
+    pub(crate) wait_timeout: Option<Duration>,
+    /// How long [`Pool::acquire`] may wait before a warning is logged, if at all
+    #[cfg(feature = "tracing")]
+    pub(crate) slow_acquire_threshold: Option<Duration>,
+    /// Whether waiters are served strictly in the order they arrived
+    pub(crate) fair: bool,
+    /// How long a waiter waits before it is served as if it had a higher priority
+    pub(crate) priority_aging: Duration,
+    /// The order in which idle values are handed out
+    pub(crate) queue_mode: QueueMode,
+    /// Whether wait and hold times are recorded for [`Pool::metrics`]
+    pub(crate) record_timings: bool,
+    /// The most values the pool may own, if more than it starts with
+    pub(crate) max_size: Option<usize>,
+    /// How many idle values [`Pool::maintain`] keeps ready
+    pub(crate) min_idle: usize,
+    /// How long a value may sit idle before [`Pool::maintain`] destroys it
+    pub(crate) idle_timeout: Option<Duration>,
+    /// How long a value may live before it is destroyed
+    pub(crate) max_lifetime: Option<Duration>,
+    /// Checked before a value is handed out
+    pub(crate) test_on_acquire: Option<Check<T>>,
+    /// Checked when a value is returned
+    pub(crate) test_on_return: Option<Check<T>>,
+    /// Clears a value when it is returned
+    pub(crate) reset: Option<Box<ResetFn<T>>>,
+    /// Callbacks for values being acquired, released, created and destroyed
+    pub(crate) hooks: Hooks<T>,
+}
+
+impl<T> Default for Config<T> {
+    fn default() -> Self {
+        Self {
+            name: None,
+            #[cfg(feature = "time")]
+            wait_timeout: None,
+            #[cfg(feature = "tracing")]
+            slow_acquire_threshold: None,
+            fair: false,
+            priority_aging: Duration::from_millis(100),
+            queue_mode: QueueMode::Fifo,
+            record_timings: false,
+            max_size: None,
+            min_idle: 0,
+            idle_timeout: None,
+            max_lifetime: None,
+            test_on_acquire: None,
+            test_on_return: None,
+            reset: None,
+            hooks: Hooks::default(),
+        }
+    }
+}
+
+impl<T> Config<T> {
+    /// The pool's name, see [`Builder::name`]
+    pub(crate) fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or("tub")
+    }
+}
+
+impl<T> Builder<T> {
+    /// Create a builder with the default settings
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Builder;
+    /// let pool = Builder::new().build(vec![1, 2, 3]);
+    /// assert_eq!(pool.remaining_capacity(), 3);
+    /// ```
+    pub fn new() -> Self {
+        Self {
+            config: Config::default(),
+        }
+    }
+
+    /// Give the pool a name.
+    ///
+    /// With the `tracing` feature, every event and span from the pool carries its name
+    /// in a `pool` field. Pools are named `"tub"` by default.
+    ///
+    /// With the `metrics` feature, the pool publishes these metrics with its name in a
+    /// `pool` label, through the recorder installed when the pool is built:
+    ///
+    /// | Metric                           | Kind    | Description                              |
+    /// |----------------------------------|---------|------------------------------------------|
+    /// | `tub_pool_idle`                  | gauge   | Values waiting in the pool               |
+    /// | `tub_pool_in_use`                | gauge   | Values held by a [`Guard`](crate::Guard) |
+    /// | `tub_pool_waiters`               | gauge   | Tasks waiting for a value                |
+    /// | `tub_pool_acquires_total`        | counter | [`Guard`](crate::Guard)s handed out      |
+    /// | `tub_pool_timeouts_total`        | counter | Acquires that timed out                  |
+    /// | `tub_pool_create_failures_total` | counter | Values the [`Manager`] failed to create  |
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    /// let pool = Pool::builder().name("connections").build(0..10);
+    /// assert_eq!(pool.name(), "connections");
+    /// ```
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.config.name = Some(name.into());
+        self
+    }
+
+    /// Set how long [`Pool::acquire`] waits for a value before failing with
+    /// [`AcquireError::Timeout`](crate::AcquireError::Timeout).
+    ///
+    /// By default [`Pool::acquire`] waits until a value is available. The timeout runs on
+    /// tokio's timer, so with a wait timeout [`Pool::acquire`] must run inside a tokio runtime.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use tub::Pool;
+    /// let pool: Pool<u32> = Pool::builder()
+    ///     .wait_timeout(Duration::from_millis(100))
+    ///     .build(0..10);
+    /// ```
+    #[cfg(feature = "time")]
+    pub fn wait_timeout(mut self, timeout: Duration) -> Self {
+        self.config.wait_timeout = Some(timeout);
+        self
+    }
+
+    /// Log a warning whenever [`Pool::acquire`] waits longer than `threshold` for a value.
+    ///
+    /// By default slow acquires are not logged.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use tub::Pool;
+    /// let pool: Pool<u32> = Pool::builder()
+    ///     .slow_acquire_threshold(Duration::from_millis(50))
+    ///     .build(0..10);
+    /// ```
+    #[cfg(feature = "tracing")]
+    pub fn slow_acquire_threshold(mut self, threshold: Duration) -> Self {
+        self.config.slow_acquire_threshold = Some(threshold);
+        self
+    }
+
+    /// Serve tasks waiting in [`Pool::acquire`] strictly in the order they started waiting.
+    ///
+    /// A value that comes back is always handed to the longest waiting task, but by
+    /// default a task that has just called [`Pool::acquire`] may still take an idle
+    /// value or create a new one ahead of the waiters. In a fair pool only the longest
+    /// waiting task may take or create a value, and new tasks line up behind it. This
+    /// costs some throughput, since a new value isn't created until the task in front
+    /// has been scheduled.
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() {
+    ///    let pool = Pool::builder().fair(true).build(vec![1]);
+    ///    let value = pool.acquire().await.unwrap();
+    ///
+    ///    let waiter = tokio::spawn({
+    ///        let pool = pool.clone();
+    ///        async move { *pool.acquire().await.unwrap() }
+    ///    });
+    ///    tokio::task::yield_now().await;
+    ///    drop(value);
+    ///
+    ///    // The returned value is saved for the task that was already waiting
+    ///    assert!(pool.try_acquire().is_none());
+    ///    assert_eq!(waiter.await.unwrap(), 1);
+    /// }
+    /// ```
+    pub fn fair(mut self, fair: bool) -> Self {
+        self.config.fair = fair;
+        self
+    }
+
+    /// Set how long a task in [`Pool::acquire_with_priority`] waits before it is served
+    /// as if it had the next higher [`Priority`](crate::Priority), 100ms by default.
+    ///
+    /// Without aging, low priority waiters would wait for as long as higher priority
+    /// tasks keep coming. With it, a waiter is overtaken for at most this long by each
+    /// priority above it. A zero interval ignores priorities, serving waiters in the
+    /// order they started waiting.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use tub::Pool;
+    ///
+    /// let pool = Pool::builder()
+    ///     .priority_aging(Duration::from_secs(1))
+    ///     .build(vec![1]);
+    /// assert_eq!(pool.remaining_capacity(), 1);
+    /// ```
+    pub fn priority_aging(mut self, interval: Duration) -> Self {
+        self.config.priority_aging = interval;
+        self
+    }
+
+    /// Set the order in which idle values are handed out, [`QueueMode::Fifo`] by default.
+    ///
+    /// A FIFO pool spreads work evenly over its values. A LIFO pool keeps reusing the
+    /// values it returned most recently, which keeps them warm, e.g. connections with
+    /// full caches. The rest stay idle and can be destroyed by [`Pool::maintain`] once
+    /// they reach the [idle timeout](Builder::idle_timeout).
+    ///
+    /// Values returned while a task is waiting go straight to that task, whatever the mode.
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::{Pool, QueueMode};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///    let pool = Pool::builder().queue_mode(QueueMode::Lifo).build(vec![1, 2, 3]);
+    ///    let value = pool.acquire().await.unwrap();
+    ///    assert_eq!(*value, 3);
+    ///
+    ///    // The value just returned is handed out again
+    ///    drop(value);
+    ///    assert_eq!(*pool.acquire().await.unwrap(), 3);
+    /// }
+    /// ```
+    pub fn queue_mode(mut self, mode: QueueMode) -> Self {
+        self.config.queue_mode = mode;
+        self
+    }
+
+    /// Record how long [`Pool::acquire`] waits and how long [`Guard`](crate::Guard)s are
+    /// held, reported by [`Pool::metrics`].
+    ///
+    /// This is off by default, since timing every [`Guard`](crate::Guard) reads the clock
+    /// each time a value is handed out and returned.
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///    let pool = Pool::builder().record_timings(true).build(vec![1]);
+    ///    drop(pool.acquire().await.unwrap());
+    ///    assert_eq!(pool.metrics().hold.count, 1);
+    /// }
+    /// ```
+    pub fn record_timings(mut self, record: bool) -> Self {
+        self.config.record_timings = record;
+        self
+    }
+
+    /// Set the most values the pool may own at once.
+    ///
+    /// By default a pool built with an initializer never grows past the number of values
+    /// it starts with, and a pool built from values has no limit on how many values can be
+    /// [added](Pool::add) later. A larger maximum lets [`Pool::acquire`] and
+    /// [`Pool::maintain`] create more values with the initializer. Pools built with
+    /// [`Builder::build_with_manager`] take their maximum size as an argument instead.
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    /// let pool = Pool::builder().max_size(20).build_with_initializer(10, || 0);
+    /// assert_eq!(pool.remaining_capacity(), 10);
+    /// ```
+    pub fn max_size(mut self, max_size: usize) -> Self {
+        self.config.max_size = Some(max_size);
+        self
+    }
+
+    /// Set how many idle values [`Pool::maintain`] keeps ready.
+    ///
+    /// Idle values are only created while the pool is below its maximum size, and
+    /// only for pools with an initializer or a [`Manager`].
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    /// let pool = Pool::builder()
+    ///     .min_idle(5)
+    ///     .max_size(10)
+    ///     .build_with_initializer(0, || 0);
+    /// ```
+    pub fn min_idle(mut self, min_idle: usize) -> Self {
+        self.config.min_idle = min_idle;
+        self
+    }
+
+    /// Set how long a value may sit idle in the pool before [`Pool::maintain`] destroys it.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use tub::Pool;
+    /// let pool = Pool::builder()
+    ///     .idle_timeout(Duration::from_secs(60))
+    ///     .build(0..10);
+    /// ```
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.config.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Set how long a value may live before the pool destroys it.
+    ///
+    /// Expired values are destroyed when their [`Guard`](crate::Guard) is dropped or
+    /// when they are found idle, and the pool replaces them on a later
+    /// [`Pool::acquire`] if it has an initializer or a [`Manager`].
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use tub::Pool;
+    /// let pool = Pool::builder()
+    ///     .max_lifetime(Duration::from_secs(30 * 60))
+    ///     .build_with_initializer(10, || 0);
+    /// ```
+    pub fn max_lifetime(mut self, lifetime: Duration) -> Self {
+        self.config.max_lifetime = Some(lifetime);
+        self
+    }
+
+    /// Check each value before [`Pool::acquire`] hands it out.
+    ///
+    /// Values that fail the check are destroyed and [`Pool::acquire`] moves on to the
+    /// next one. This replaces any earlier acquire check.
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///    let pool = Pool::builder()
+    ///        .test_on_acquire(|value: &mut u32| *value != 0)
+    ///        .build(vec![0, 1]);
+    ///    assert_eq!(*pool.acquire().await.unwrap(), 1);
+    /// }
+    /// ```
+    pub fn test_on_acquire<F>(mut self, check: F) -> Self
+    where
+        F: Fn(&mut T) -> bool + Send + Sync + 'static,
+    {
+        self.config.test_on_acquire = Some(Check::Sync(Box::new(check)));
+        self
+    }
+
+    /// Check each value with an asynchronous test before [`Pool::acquire`] hands it out.
+    ///
+    /// This works like [`Builder::test_on_acquire`], for tests that need to await,
+    /// such as pinging a connection. [`Pool::try_acquire`] can't await, so it only polls
+    /// this test once and leaves the value idle if the test isn't done by then.
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///    let pool = Pool::builder()
+    ///        .test_on_acquire_async(|value: &mut u32| Box::pin(async move { *value != 0 }))
+    ///        .build(vec![0, 1]);
+    ///    assert_eq!(*pool.acquire().await.unwrap(), 1);
+    /// }
+    /// ```
+    pub fn test_on_acquire_async<F>(mut self, check: F) -> Self
+    where
+        F: for<'a> Fn(&'a mut T) -> BoxFuture<'a, bool> + Send + Sync + 'static,
+    {
+        self.config.test_on_acquire = Some(Check::Async(Box::new(check)));
+        self
+    }
+
+    /// Check each value when its [`Guard`](crate::Guard) is dropped.
+    ///
+    /// Values that fail the check are destroyed instead of going back into the pool.
+    /// This replaces any earlier return check.
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///    let pool = Pool::builder()
+    ///        .test_on_return(|buffer: &mut Vec<u8>| buffer.capacity() <= 1024)
+    ///        .build(vec![Vec::new()]);
+    ///
+    ///    let mut buffer = pool.acquire().await.unwrap();
+    ///    buffer.reserve(4096);
+    ///    drop(buffer);
+    ///    assert_eq!(pool.remaining_capacity(), 0);
+    /// }
+    /// ```
+    pub fn test_on_return<F>(mut self, check: F) -> Self
+    where
+        F: Fn(&mut T) -> bool + Send + Sync + 'static,
+    {
+        self.config.test_on_return = Some(Check::Sync(Box::new(check)));
+        self
+    }
+
+    /// Check each returned value with an asynchronous test.
+    ///
+    /// Dropping a [`Guard`](crate::Guard) can't await, so the value goes back into the
+    /// pool and the test runs the next time [`Pool::acquire`] takes it out. Values that
+    /// fail are destroyed and [`Pool::acquire`] moves on to the next one.
+    /// This replaces any earlier return check.
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///    let pool = Pool::builder()
+    ///        .test_on_return_async(|value: &mut u32| Box::pin(async move { *value < 10 }))
+    ///        .build_with_initializer(1, || 0);
+    ///
+    ///    let mut value = pool.acquire().await.unwrap();
+    ///    *value = 10;
+    ///    drop(value);
+    ///
+    ///    // The bad value is destroyed and replaced by the initializer
+    ///    assert_eq!(*pool.acquire().await.unwrap(), 0);
+    /// }
+    /// ```
+    pub fn test_on_return_async<F>(mut self, check: F) -> Self
+    where
+        F: for<'a> Fn(&'a mut T) -> BoxFuture<'a, bool> + Send + Sync + 'static,
+    {
+        self.config.test_on_return = Some(Check::Async(Box::new(check)));
+        self
+    }
+
+    /// Call `reset` on each value when its [`Guard`](crate::Guard) is dropped, before
+    /// the value goes back into the pool.
+    ///
+    /// The reset runs before the [return check](Builder::test_on_return).
+    /// This replaces any earlier reset.
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///    let pool = Pool::builder()
+    ///        .reset(|value: &mut u32| *value = 0)
+    ///        .build(vec![0]);
+    ///
+    ///    *pool.acquire().await.unwrap() = 123;
+    ///    assert_eq!(*pool.acquire().await.unwrap(), 0);
+    /// }
+    /// ```
+    pub fn reset<F>(mut self, reset: F) -> Self
+    where
+        F: Fn(&mut T) + Send + Sync + 'static,
+    {
+        self.config.reset = Some(Box::new(reset));
+        self
+    }
+
+    /// Clear each value with [`Reset::reset`] when its [`Guard`](crate::Guard) is dropped.
+    ///
+    /// This is shorthand for `.reset(T::reset)`.
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///    let pool = Pool::builder()
+    ///        .reset_on_return()
+    ///        .build(vec![Vec::<u8>::with_capacity(1024)]);
+    ///
+    ///    pool.acquire().await.unwrap().extend_from_slice(b"hello");
+    ///
+    ///    let buffer = pool.acquire().await.unwrap();
+    ///    assert!(buffer.is_empty());
+    ///    assert!(buffer.capacity() >= 1024);
+    /// }
+    /// ```
+    pub fn reset_on_return(self) -> Self
+    where
+        T: Reset + 'static,
+    {
+        self.reset(T::reset)
+    }
+
+    /// Call `hook` whenever a [`Guard`](crate::Guard) is handed out, with how long
+    /// the caller waited for it.
+    ///
+    /// This replaces any earlier hook.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use tub::Pool;
+    ///
+    /// let pool = Pool::builder()
+    ///     .on_acquire(|value: &u32, metadata| {
+    ///         if metadata.waited > Some(Duration::from_secs(1)) {
+    ///             eprintln!("waited too long for value {value}");
+    ///         }
+    ///     })
+    ///     .build(vec![1, 2]);
+    /// ```
+    pub fn on_acquire<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&T, &Metadata) + Send + Sync + 'static,
+    {
+        self.config.hooks.on_acquire = Some(Box::new(hook));
+        self
+    }
+
+    /// Call `hook` whenever a [`Guard`](crate::Guard) is dropped or
+    /// [detached](crate::Guard::detach), with how long it was held.
+    ///
+    /// The hook runs before the value is [reset](Builder::reset) or checked.
+    /// This replaces any earlier hook.
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    ///
+    /// let pool = Pool::builder()
+    ///     .on_release(|_: &u32, metadata| println!("held for {:?}", metadata.held))
+    ///     .build(vec![1, 2]);
+    /// ```
+    pub fn on_release<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&T, &Metadata) + Send + Sync + 'static,
+    {
+        self.config.hooks.on_release = Some(Box::new(hook));
+        self
+    }
+
+    /// Call `hook` whenever the pool's initializer or [`Manager`] creates a value,
+    /// including the values a pool built with an initializer starts with.
+    ///
+    /// This replaces any earlier hook.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    /// use tub::Pool;
+    ///
+    /// let created = Arc::new(AtomicUsize::new(0));
+    /// let pool = Pool::builder()
+    ///     .on_create({
+    ///         let created = created.clone();
+    ///         move |_: &u32, _| {
+    ///             created.fetch_add(1, Ordering::SeqCst);
+    ///         }
+    ///     })
+    ///     .build_with_initializer(4, || 0);
+    /// assert_eq!(created.load(Ordering::SeqCst), 4);
+    /// ```
+    pub fn on_create<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&T, &Metadata) + Send + Sync + 'static,
+    {
+        self.config.hooks.on_create = Some(Box::new(hook));
+        self
+    }
+
+    /// Call `hook` whenever the pool destroys a value, e.g. because it expired,
+    /// failed a check, or the pool was closed.
+    ///
+    /// The hook runs before [`Manager::detach`]. Values taken out of the pool with
+    /// [`Guard::detach`](crate::Guard::detach), [`Pool::remove_idle`] or [`Pool::drain`]
+    /// are not destroyed, so the hook doesn't run for them.
+    /// This replaces any earlier hook.
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    ///
+    /// let pool = Pool::builder()
+    ///     .on_destroy(|_: &u32, metadata| println!("destroyed value {}", metadata.id))
+    ///     .build(vec![1, 2]);
+    /// pool.close();
+    /// ```
+    pub fn on_destroy<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&T, &Metadata) + Send + Sync + 'static,
+    {
+        self.config.hooks.on_destroy = Some(Box::new(hook));
+        self
+    }
+
+    /// Build a pool containing the given values
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    /// let pool = Pool::builder().build(0..10);
+    /// assert_eq!(pool.remaining_capacity(), 10);
+    /// ```
+    pub fn build<I>(self, values: I) -> Pool<T>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let values: Vec<T> = values.into_iter().collect();
+        let max_size = self.config.max_size.unwrap_or(usize::MAX).max(values.len());
+        Pool::from_parts(max_size, values, None, Arc::new(self.config), None)
+    }
+
+    /// Build a pool of `capacity` values created by an initializer
+    ///
+    /// The pool keeps the initializer to replace values that are destroyed, and to create
+    /// more values if its [maximum size](Builder::max_size) is larger than `capacity`.
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    /// let pool = Pool::builder().build_with_initializer(10, || 0);
+    /// assert_eq!(pool.remaining_capacity(), 10);
+    /// ```
+    pub fn build_with_initializer<F>(self, capacity: usize, init: F) -> Pool<T>
+    where
+        F: Fn() -> T + Send + Sync + 'static,
+    {
+        let values = (0..capacity).map(|_| init()).collect();
+        let max_size = self.config.max_size.unwrap_or(0).max(capacity);
+        let source = Source::Initializer(Box::new(init));
+        Pool::from_parts(max_size, values, Some(source), Arc::new(self.config), None)
+    }
+
+    /// Build an empty pool whose values are created on demand by a [`Manager`],
+    /// up to `max_size` values
+    ///
+    /// # Examples
+    /// ```
+    /// use std::convert::Infallible;
+    /// use tub::{Manager, Pool};
+    ///
+    /// struct Buffers;
+    ///
+    /// impl Manager for Buffers {
+    ///     type Type = Vec<u8>;
+    ///     type Error = Infallible;
+    ///
+    ///     async fn create(&self) -> Result<Vec<u8>, Infallible> {
+    ///         Ok(Vec::with_capacity(1024))
+    ///     }
+    /// }
+    ///
+    /// let pool = Pool::builder().build_with_manager(Buffers, 16);
+    /// assert_eq!(pool.remaining_capacity(), 0);
+    /// ```
+    pub fn build_with_manager<M>(self, manager: M, max_size: usize) -> Pool<T>
+    where
+        M: Manager<Type = T>,
+    {
+        let source = Source::Manager(Box::new(manager));
+        Pool::from_parts(
+            max_size,
+            Vec::new(),
+            Some(source),
+            Arc::new(self.config),
+            None,
+        )
+    }
+
+    /// Build a [`ShardedPool`] with the given values spread over `shards` shards
+    ///
+    /// A [maximum size](Builder::max_size) is split evenly between the shards.
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    /// let pool = Pool::builder().build_sharded(0..10, 4);
+    /// assert_eq!(pool.shards().len(), 4);
+    /// assert_eq!(pool.remaining_capacity(), 10);
+    /// ```
+    pub fn build_sharded<I>(self, values: I, shards: usize) -> ShardedPool<T>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let max_size = self.config.max_size;
+        let values = values.into_iter().collect();
+        ShardedPool::from_parts(shards, values, max_size, self.config, || None)
+    }
+
+    /// Build a [`ShardedPool`] of `capacity` values created by an initializer, spread
+    /// over `shards` shards
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    /// let pool = Pool::builder().build_sharded_with_initializer(10, || 0, 4);
+    /// assert_eq!(pool.remaining_capacity(), 10);
+    /// ```
+    pub fn build_sharded_with_initializer<F>(
+        self,
+        capacity: usize,
+        init: F,
+        shards: usize,
+    ) -> ShardedPool<T>
+    where
+        F: Fn() -> T + Send + Sync + 'static,
+    {
+        let values = (0..capacity).map(|_| init()).collect();
+        let max_size = self.config.max_size.unwrap_or(0).max(capacity);
+        let init = Arc::new(init);
+        let source = || {
+            let init = init.clone();
+            Some(Source::Initializer(Box::new(move || init())))
+        };
+        ShardedPool::from_parts(shards, values, Some(max_size), self.config, source)
+    }
+
+    /// Build an empty [`ShardedPool`] with `shards` shards, whose values are created on
+    /// demand by a clone of `manager` for each shard, up to `max_size` values in total
+    ///
+    /// # Examples
+    /// ```
+    /// use std::convert::Infallible;
+    /// use tub::{Manager, Pool};
+    ///
+    /// #[derive(Clone)]
+    /// struct Buffers;
+    ///
+    /// impl Manager for Buffers {
+    ///     type Type = Vec<u8>;
+    ///     type Error = Infallible;
+    ///
+    ///     async fn create(&self) -> Result<Vec<u8>, Infallible> {
+    ///         Ok(Vec::with_capacity(1024))
+    ///     }
+    /// }
+    ///
+    /// let pool = Pool::builder().build_sharded_with_manager(Buffers, 16, 4);
+    /// assert_eq!(pool.remaining_capacity(), 0);
+    /// ```
+    pub fn build_sharded_with_manager<M>(
+        self,
+        manager: M,
+        max_size: usize,
+        shards: usize,
+    ) -> ShardedPool<T>
+    where
+        M: Manager<Type = T> + Clone,
+    {
+        let source = || Some(Source::Manager(Box::new(manager.clone())));
+        ShardedPool::from_parts(shards, Vec::new(), Some(max_size), self.config, source)
+    }
+
+    /// Build a [`KeyedPool`] with a pool for each key, whose values are created on
+    /// demand by the [`Manager`] returned by `manager` for that key.
+    ///
+    /// A key's pool may own up to `per_key` values, and all of them together up to
+    /// `max_size` values. Every key's pool gets the settings of this builder.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::convert::Infallible;
+    /// use std::time::Duration;
+    /// use tub::{Manager, Pool};
+    ///
+    /// struct Connect(String);
+    ///
+    /// impl Manager for Connect {
+    ///     type Type = String;
+    ///     type Error = Infallible;
+    ///
+    ///     async fn create(&self) -> Result<String, Infallible> {
+    ///         Ok(self.0.clone())
+    ///     }
+    /// }
+    ///
+    /// let pool = Pool::builder()
+    ///     .idle_timeout(Duration::from_secs(60))
+    ///     .build_keyed(|host: &String| Connect(host.clone()), 4, 64);
+    /// assert!(pool.is_empty());
+    /// ```
+    pub fn build_keyed<K, F, M>(
+        self,
+        manager: F,
+        per_key: usize,
+        max_size: usize,
+    ) -> KeyedPool<K, T>
+    where
+        K: Eq + Hash + Clone + Send + Sync + 'static,
+        T: Send + 'static,
+        F: Fn(&K) -> M + Send + Sync + 'static,
+        M: Manager<Type = T>,
+    {
+        let factory = move |key: &K| Source::Manager(Box::new(manager(key)));
+        KeyedPool::new(Box::new(factory), per_key, max_size, self.config)
+    }
+}
+
+impl<T> Default for Builder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,29 @@
+use crate::manager::BoxFuture;
+
+type AsyncCheck<T> = dyn for<'a> Fn(&'a mut T) -> BoxFuture<'a, bool> + Send + Sync;
+
+/// A test that decides whether a value may stay in the pool
+pub(crate) enum Check<T> {
+    /// A test that runs to completion immediately
+    Sync(Box<dyn Fn(&mut T) -> bool + Send + Sync>),
+    /// A test that needs to be awaited, e.g. a network round trip
+    Async(Box<AsyncCheck<T>>),
+}
+
+impl<T> Check<T> {
+    /// Run the test
+    pub(crate) async fn run(&self, value: &mut T) -> bool {
+        match self {
+            Check::Sync(check) => check(value),
+            Check::Async(check) => check(value).await,
+        }
+    }
+
+    /// Run the test if it doesn't need to be awaited
+    pub(crate) fn run_sync(&self, value: &mut T) -> Option<bool> {
+        match self {
+            Check::Sync(check) => Some(check(value)),
+            Check::Async(_) => None,
+        }
+    }
+}
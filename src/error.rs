@@ -0,0 +1,54 @@
+use crate::manager::BoxError;
+use std::error::Error;
+use std::fmt;
+
+/// The reason a value could not be acquired from a [`Pool`](crate::Pool)
+///
+/// # Examples
+///
+/// ```
+/// use tub::{AcquireError, Guard, Pool};
+///
+/// #[tokio::main]
+/// async fn main() {
+///   let pool = Pool::from_vec(vec![1]);
+///   let value = pool.acquire().await.unwrap();
+///
+///   // The only value left the pool for good, so waiting for another one is pointless
+///   Guard::detach(value);
+///   let result = pool.acquire().await;
+///   assert!(matches!(result, Err(AcquireError::Exhausted)));
+/// }
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum AcquireError {
+    /// No value became available before the deadline
+    Timeout,
+    /// The pool has been closed
+    Closed,
+    /// The pool has no values and can never get one back, so waiting would never finish
+    Exhausted,
+    /// The pool's [`Manager`](crate::Manager) failed to create a new value
+    Create(BoxError),
+}
+
+impl fmt::Display for AcquireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AcquireError::Timeout => f.write_str("timed out waiting for a pooled value"),
+            AcquireError::Closed => f.write_str("the pool is closed"),
+            AcquireError::Exhausted => f.write_str("the pool has no values"),
+            AcquireError::Create(e) => write!(f, "failed to create a pooled value: {e}"),
+        }
+    }
+}
+
+impl Error for AcquireError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AcquireError::Create(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! Publishing through the `metrics` facade, which compiles to nothing without the
+//! `metrics` feature. The metrics are listed in [`Builder::name`](crate::Builder::name).
+
+#[cfg(feature = "metrics")]
+use ::metrics::{counter, gauge, Counter, Gauge};
+
+/// Handles to a pool's metrics, registered with the recorder installed when the pool
+/// was built
+pub(crate) struct Exporter {
+    #[cfg(feature = "metrics")]
+    idle: Gauge,
+    #[cfg(feature = "metrics")]
+    in_use: Gauge,
+    #[cfg(feature = "metrics")]
+    waiters: Gauge,
+    #[cfg(feature = "metrics")]
+    acquires: Counter,
+    #[cfg(feature = "metrics")]
+    timeouts: Counter,
+    #[cfg(feature = "metrics")]
+    create_failures: Counter,
+}
+
+#[cfg(feature = "metrics")]
+impl Exporter {
+    pub(crate) fn new(pool: &str, idle: usize) -> Self {
+        let label = || ("pool", pool.to_owned());
+        let exporter = Exporter {
+            idle: gauge!("tub_pool_idle", &[label()]),
+            in_use: gauge!("tub_pool_in_use", &[label()]),
+            waiters: gauge!("tub_pool_waiters", &[label()]),
+            acquires: counter!("tub_pool_acquires_total", &[label()]),
+            timeouts: counter!("tub_pool_timeouts_total", &[label()]),
+            create_failures: counter!("tub_pool_create_failures_total", &[label()]),
+        };
+        exporter.idle.increment(idle as f64);
+        exporter
+    }
+
+    /// Add `n` to the number of idle values, or subtract it if `n` is negative
+    pub(crate) fn idle(&self, n: isize) {
+        self.idle.increment(n as f64);
+    }
+
+    /// Add `n` to the number of values in use, or subtract it if `n` is negative
+    pub(crate) fn in_use(&self, n: isize) {
+        self.in_use.increment(n as f64);
+    }
+
+    /// Add `n` to the number of waiters, or subtract it if `n` is negative
+    pub(crate) fn waiters(&self, n: isize) {
+        self.waiters.increment(n as f64);
+    }
+
+    pub(crate) fn acquired(&self) {
+        self.acquires.increment(1);
+    }
+
+    pub(crate) fn timed_out(&self) {
+        self.timeouts.increment(1);
+    }
+
+    pub(crate) fn create_failed(&self) {
+        self.create_failures.increment(1);
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+impl Exporter {
+    #[inline(always)]
+    pub(crate) fn new(_pool: &str, _idle: usize) -> Self {
+        Exporter {}
+    }
+
+    #[inline(always)]
+    pub(crate) fn idle(&self, _n: isize) {}
+
+    #[inline(always)]
+    pub(crate) fn in_use(&self, _n: isize) {}
+
+    #[inline(always)]
+    pub(crate) fn waiters(&self, _n: isize) {}
+
+    #[inline(always)]
+    pub(crate) fn acquired(&self) {}
+
+    #[inline(always)]
+    pub(crate) fn timed_out(&self) {}
+
+    #[inline(always)]
+    pub(crate) fn create_failed(&self) {}
+}
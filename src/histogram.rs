@@ -0,0 +1,133 @@
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering::Relaxed;
+use std::time::Duration;
+
+/// Each power of two is split into this many buckets, so a percentile is reported
+/// at most 25% above the true value
+const SUB_BUCKETS: usize = 4;
+const SUB_BUCKET_BITS: u32 = SUB_BUCKETS.trailing_zeros();
+/// Enough buckets for every `u64` number of nanoseconds
+const BUCKETS: usize = (64 - SUB_BUCKET_BITS as usize + 1) * SUB_BUCKETS;
+
+/// Percentiles of a duration measured by a [`Pool`](crate::Pool)
+///
+/// Durations are recorded in buckets rather than one by one, so the percentiles
+/// are rounded up to the end of their bucket, at most 25% above the true value.
+/// [`Percentiles::max`] is exact.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Percentiles {
+    /// The number of durations recorded
+    pub count: u64,
+    /// The median
+    pub p50: Duration,
+    /// The 90th percentile
+    pub p90: Duration,
+    /// The 99th percentile
+    pub p99: Duration,
+    /// The longest duration recorded
+    pub max: Duration,
+}
+
+/// Timing metrics of a [`Pool`](crate::Pool), from [`Pool::metrics`](crate::Pool::metrics)
+///
+/// # Examples
+///
+/// ```
+/// use tub::Pool;
+///
+/// #[tokio::main]
+/// async fn main() {
+///   let pool = Pool::builder().record_timings(true).build(vec![0; 10]);
+///   drop(pool.acquire().await.unwrap());
+///
+///   let metrics = pool.metrics();
+///   assert_eq!(metrics.wait.count, 1);
+///   assert_eq!(metrics.hold.count, 1);
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PoolMetrics {
+    /// How long [`Pool::acquire`](crate::Pool::acquire) waited for a value before it
+    /// returned a [`Guard`](crate::Guard), zero if one was ready
+    pub wait: Percentiles,
+    /// How long [`Guard`](crate::Guard)s were held before they were dropped or detached
+    pub hold: Percentiles,
+}
+
+/// A lock-free histogram of durations, with buckets growing exponentially
+pub(crate) struct Histogram {
+    buckets: [AtomicU64; BUCKETS],
+    max: AtomicU64,
+}
+
+impl Histogram {
+    pub(crate) fn new() -> Self {
+        Histogram {
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+            max: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn record(&self, duration: Duration) {
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        self.buckets[bucket(nanos)].fetch_add(1, Relaxed);
+        self.max.fetch_max(nanos, Relaxed);
+    }
+
+    pub(crate) fn percentiles(&self) -> Percentiles {
+        let counts: Vec<u64> = self.buckets.iter().map(|b| b.load(Relaxed)).collect();
+        let count = counts.iter().sum();
+        let max = self.max.load(Relaxed);
+
+        // Find the bucket holding the `q`th quantile, counting from the shortest durations
+        let quantile = |q: f64| {
+            let rank = ((count as f64 * q).ceil() as u64).max(1);
+            let mut seen = 0;
+            for (index, &n) in counts.iter().enumerate() {
+                seen += n;
+                if seen >= rank {
+                    return Duration::from_nanos(upper_bound(index).min(max));
+                }
+            }
+            Duration::from_nanos(max)
+        };
+
+        if count == 0 {
+            return Percentiles::default();
+        }
+
+        Percentiles {
+            count,
+            p50: quantile(0.50),
+            p90: quantile(0.90),
+            p99: quantile(0.99),
+            max: Duration::from_nanos(max),
+        }
+    }
+}
+
+/// The index of the bucket holding `nanos`
+fn bucket(nanos: u64) -> usize {
+    if nanos < SUB_BUCKETS as u64 {
+        return nanos as usize;
+    }
+
+    // The highest bit picks the power of two, and the bits below it the sub-bucket
+    let exponent = 63 - nanos.leading_zeros();
+    let sub = (nanos >> (exponent - SUB_BUCKET_BITS)) as usize & (SUB_BUCKETS - 1);
+    (exponent - SUB_BUCKET_BITS + 1) as usize * SUB_BUCKETS + sub
+}
+
+/// The largest number of nanoseconds in the bucket at `index`
+fn upper_bound(index: usize) -> u64 {
+    if index < SUB_BUCKETS {
+        return index as u64;
+    }
+
+    let shift = (index / SUB_BUCKETS - 1) as u32;
+    let sub = (index % SUB_BUCKETS) as u64;
+    let lower = (SUB_BUCKETS as u64 + sub) << shift;
+    lower + ((1 << shift) - 1)
+}
@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+type HookFn<T> = dyn Fn(&T, &Metadata) + Send + Sync;
+
+/// What a lifecycle hook knows about a value, besides the value itself
+///
+/// Hooks are set with [`Builder::on_acquire`](crate::Builder::on_acquire),
+/// [`Builder::on_release`](crate::Builder::on_release),
+/// [`Builder::on_create`](crate::Builder::on_create) and
+/// [`Builder::on_destroy`](crate::Builder::on_destroy).
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::atomic::{AtomicU64, Ordering};
+/// use std::sync::Arc;
+/// use tub::Pool;
+///
+/// #[tokio::main]
+/// async fn main() {
+///   let last = Arc::new(AtomicU64::new(u64::MAX));
+///   let pool = Pool::builder()
+///       .on_release({
+///           let last = last.clone();
+///           move |_: &u32, metadata| last.store(metadata.id, Ordering::SeqCst)
+///       })
+///       .build(vec![7]);
+///
+///   drop(pool.acquire().await.unwrap());
+///   assert_eq!(last.load(Ordering::SeqCst), 0);
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Metadata {
+    /// Identifies the value among every value the pool has owned
+    pub id: u64,
+    /// How long [`Pool::acquire`](crate::Pool::acquire) waited for the value,
+    /// only set for [`Builder::on_acquire`](crate::Builder::on_acquire)
+    pub waited: Option<Duration>,
+    /// How long the value was held by a [`Guard`](crate::Guard),
+    /// only set for [`Builder::on_release`](crate::Builder::on_release)
+    pub held: Option<Duration>,
+}
+
+impl Metadata {
+    pub(crate) fn new(id: u64) -> Self {
+        Metadata {
+            id,
+            waited: None,
+            held: None,
+        }
+    }
+}
+
+/// Callbacks run as values move through the pool
+pub(crate) struct Hooks<T> {
+    pub(crate) on_acquire: Option<Box<HookFn<T>>>,
+    pub(crate) on_release: Option<Box<HookFn<T>>>,
+    pub(crate) on_create: Option<Box<HookFn<T>>>,
+    pub(crate) on_destroy: Option<Box<HookFn<T>>>,
+}
+
+impl<T> Default for Hooks<T> {
+    fn default() -> Self {
+        Self {
+            on_acquire: None,
+            on_release: None,
+            on_create: None,
+            on_destroy: None,
+        }
+    }
+}
+
+/// Run a hook, if it is set
+pub(crate) fn run<T>(hook: &Option<Box<HookFn<T>>>, value: &T, metadata: Metadata) {
+    if let Some(hook) = hook {
+        hook(value, &metadata);
+    }
+}
@@ -0,0 +1,396 @@
+use crate::builder::Config;
+use crate::manager::Source;
+use crate::{AcquireError, Builder, Guard, Manager, Pool, PoolInner};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ptr;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::{AcqRel, Acquire};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, Weak};
+#[cfg(feature = "rt")]
+use std::time::Duration;
+
+type Factory<K, T> = dyn Fn(&K) -> Source<T> + Send + Sync;
+
+/// A pool for each key, such as a pool of connections for each host
+///
+/// Each key gets its own [`Pool`] the first time it is used, with values created by a
+/// [`Manager`] for that key. A key's pool may own up to a fixed number of values, and all
+/// of them together may own up to a global maximum. When a key needs a new value but the
+/// global maximum has been reached, a value idle in another key's pool is destroyed to make
+/// room, or the caller waits until one is returned.
+///
+/// Values are protected by a [`Guard`] and go back to their key's pool when dropped, just
+/// like values from a [`Pool`]. Every key's pool has the settings of the [`Builder`] it was
+/// built with, so [`Builder::idle_timeout`] makes [`KeyedPool::maintain`] destroy idle
+/// values. A key whose pool is left without any values is forgotten.
+///
+/// # Examples
+///
+/// ```
+/// use std::convert::Infallible;
+/// use tub::{KeyedPool, Manager};
+///
+/// struct Connect(&'static str);
+///
+/// impl Manager for Connect {
+///     type Type = String;
+///     type Error = Infallible;
+///
+///     async fn create(&self) -> Result<String, Infallible> {
+///         Ok(format!("connection to {}", self.0))
+///     }
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///   // Up to 2 connections to each host, and 10 in total
+///   let pool = KeyedPool::with_manager(|host: &&'static str| Connect(host), 2, 10);
+///
+///   let connection = pool.acquire(&"example.com").await.unwrap();
+///   assert_eq!(*connection, "connection to example.com");
+///   assert_eq!(pool.len(), 1);
+/// }
+/// ```
+pub struct KeyedPool<K, T> {
+    inner: Arc<KeyedInner<K, T>>,
+}
+
+impl<K, T> Clone for KeyedPool<K, T> {
+    fn clone(&self) -> Self {
+        KeyedPool {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+struct KeyedInner<K, T> {
+    pools: Mutex<HashMap<K, Pool<T>>>,
+    /// Creates the source of values for a new key
+    factory: Box<Factory<K, T>>,
+    /// The most values a key's pool may own at once
+    per_key: usize,
+    /// Settings for every key's pool
+    config: Arc<Config<T>>,
+    budget: Arc<Budget<T>>,
+}
+
+impl<K, T> KeyedPool<K, T>
+where
+    K: Eq + Hash + Clone + Send + Sync + 'static,
+    T: Send + 'static,
+{
+    /// Create a keyed pool whose values are created by the [`Manager`] returned by
+    /// `manager` for each key.
+    ///
+    /// A key's pool may own up to `per_key` values, and all of them together up to
+    /// `max_size` values.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::convert::Infallible;
+    /// use tub::{KeyedPool, Manager};
+    ///
+    /// struct Shard(u32);
+    ///
+    /// impl Manager for Shard {
+    ///     type Type = u32;
+    ///     type Error = Infallible;
+    ///
+    ///     async fn create(&self) -> Result<u32, Infallible> {
+    ///         Ok(self.0)
+    ///     }
+    /// }
+    ///
+    /// let pool = KeyedPool::with_manager(|shard: &u32| Shard(*shard), 4, 16);
+    /// assert!(pool.is_empty());
+    /// ```
+    pub fn with_manager<F, M>(manager: F, per_key: usize, max_size: usize) -> Self
+    where
+        F: Fn(&K) -> M + Send + Sync + 'static,
+        M: Manager<Type = T>,
+    {
+        Builder::new().build_keyed(manager, per_key, max_size)
+    }
+
+    pub(crate) fn new(
+        factory: Box<Factory<K, T>>,
+        per_key: usize,
+        max_size: usize,
+        config: Config<T>,
+    ) -> Self {
+        KeyedPool {
+            inner: Arc::new(KeyedInner {
+                pools: Mutex::new(HashMap::new()),
+                factory,
+                per_key,
+                config: Arc::new(config),
+                budget: Arc::new(Budget::new(max_size)),
+            }),
+        }
+    }
+
+    /// Acquire a value for `key`, like [`Pool::acquire`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`Pool::acquire`].
+    ///
+    /// # Examples
+    /// ```
+    /// use std::convert::Infallible;
+    /// use tub::{KeyedPool, Manager};
+    ///
+    /// struct Shard(u32);
+    ///
+    /// impl Manager for Shard {
+    ///     type Type = u32;
+    ///     type Error = Infallible;
+    ///
+    ///     async fn create(&self) -> Result<u32, Infallible> {
+    ///         Ok(self.0)
+    ///     }
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///    let pool = KeyedPool::with_manager(|shard: &u32| Shard(*shard), 4, 16);
+    ///    assert_eq!(*pool.acquire(&1).await.unwrap(), 1);
+    ///    assert_eq!(*pool.acquire(&2).await.unwrap(), 2);
+    /// }
+    /// ```
+    pub async fn acquire(&self, key: &K) -> Result<Guard<T>, AcquireError> {
+        self.pool(key).acquire().await
+    }
+
+    /// Try to acquire an idle value for `key` without waiting, like [`Pool::try_acquire`].
+    ///
+    /// Returns [`None`] if the key has no idle value, which includes a key that hasn't
+    /// been used yet.
+    pub fn try_acquire(&self, key: &K) -> Option<Guard<T>> {
+        let pool = self.inner.lock().get(key).cloned()?;
+        pool.try_acquire()
+    }
+
+    /// Get the pool for `key`, creating it if the key hasn't been used yet.
+    ///
+    /// The pool can be used like any other, e.g. to check its [`Pool::status`].
+    pub fn pool(&self, key: &K) -> Pool<T> {
+        let inner = &self.inner;
+        if let Some(pool) = inner.lock().get(key) {
+            return pool.clone();
+        }
+
+        // The factory may call back into this pool, so it runs without the lock
+        let source = (inner.factory)(key);
+        let budget = Some(inner.budget.clone());
+        let pool = Pool::from_parts(
+            inner.per_key,
+            Vec::new(),
+            Some(source),
+            inner.config.clone(),
+            budget,
+        );
+        let key = key.clone();
+
+        let mut pools = inner.lock();
+        // Another caller may have added the key meanwhile, and its pool wins
+        if let Some(existing) = pools.get(&key) {
+            let existing = existing.clone();
+            drop(pools);
+            return existing;
+        }
+        inner.budget.join(&pool.inner);
+        pools.insert(key, pool.clone());
+        pool
+    }
+
+    /// Get the number of keys with a pool
+    pub fn len(&self) -> usize {
+        self.inner.lock().len()
+    }
+
+    /// Whether no key has a pool
+    pub fn is_empty(&self) -> bool {
+        self.inner.lock().is_empty()
+    }
+
+    /// Get the number of values owned by every key's pool together
+    pub fn size(&self) -> usize {
+        self.inner.budget.used.load(Acquire)
+    }
+
+    /// Run [`Pool::maintain`] for every key, then forget the keys whose pools are left
+    /// without any values.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::convert::Infallible;
+    /// use std::time::Duration;
+    /// use tub::{KeyedPool, Manager, Pool};
+    ///
+    /// struct Shard(u32);
+    ///
+    /// impl Manager for Shard {
+    ///     type Type = u32;
+    ///     type Error = Infallible;
+    ///
+    ///     async fn create(&self) -> Result<u32, Infallible> {
+    ///         Ok(self.0)
+    ///     }
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///    let pool: KeyedPool<u32, u32> = Pool::builder()
+    ///        .idle_timeout(Duration::from_millis(10))
+    ///        .build_keyed(|shard: &u32| Shard(*shard), 4, 16);
+    ///    drop(pool.acquire(&1).await.unwrap());
+    ///
+    ///    tokio::time::sleep(Duration::from_millis(20)).await;
+    ///    pool.maintain().await;
+    ///    assert!(pool.is_empty());
+    /// }
+    /// ```
+    pub async fn maintain(&self) {
+        let pools: Vec<_> = self.inner.lock().values().cloned().collect();
+        for pool in pools {
+            pool.maintain().await;
+        }
+
+        // Nothing else can reach a pool held only by the map, so it can't gain values
+        // while we look at it. Dropping the last handle to a pool drops its manager,
+        // which has to wait until the lock is released.
+        let mut forgotten = Vec::new();
+        self.inner.lock().retain(|_, pool| {
+            let keep = Arc::strong_count(&pool.inner) > 1 || pool.inner.size.load(Acquire) > 0;
+            if !keep {
+                forgotten.push(pool.clone());
+            }
+            keep
+        });
+        drop(forgotten);
+    }
+}
+
+#[cfg(feature = "rt")]
+impl<K, T> KeyedPool<K, T>
+where
+    K: Eq + Hash + Clone + Send + Sync + 'static,
+    T: Send + 'static,
+{
+    /// Spawn a tokio task that calls [`KeyedPool::maintain`] every `interval`.
+    ///
+    /// The task stops on its own once every handle to the pool has been dropped.
+    pub fn spawn_maintenance(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let inner: Weak<KeyedInner<K, T>> = Arc::downgrade(&self.inner);
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            loop {
+                ticks.tick().await;
+                match inner.upgrade() {
+                    Some(inner) => KeyedPool { inner }.maintain().await,
+                    None => return,
+                }
+            }
+        })
+    }
+}
+
+impl<K, T> KeyedInner<K, T> {
+    fn lock(&self) -> MutexGuard<'_, HashMap<K, Pool<T>>> {
+        // The lock is only held to look up and update the map, never while running a
+        // factory or dropping a pool, so poisoning can be ignored
+        self.pools.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Room shared by the pools of a [`KeyedPool`], so that together they never own more
+/// than `max_size` values
+pub(crate) struct Budget<T> {
+    max_size: usize,
+    /// The number of values owned by every pool together
+    used: AtomicUsize,
+    /// The pools sharing the budget
+    pools: Mutex<Vec<Weak<PoolInner<T>>>>,
+}
+
+impl<T> Budget<T> {
+    fn new(max_size: usize) -> Self {
+        Budget {
+            max_size,
+            used: AtomicUsize::new(0),
+            pools: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub(crate) fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// Start sharing the budget with `pool`
+    fn join(&self, pool: &Arc<PoolInner<T>>) {
+        let mut pools = self.pools.lock().unwrap_or_else(PoisonError::into_inner);
+        pools.push(Arc::downgrade(pool));
+    }
+
+    /// Get every pool sharing the budget that is still alive
+    fn pools(&self) -> Vec<Arc<PoolInner<T>>> {
+        let mut pools = self.pools.lock().unwrap_or_else(PoisonError::into_inner);
+        pools.retain(|pool| pool.strong_count() > 0);
+        pools.iter().filter_map(Weak::upgrade).collect()
+    }
+
+    /// Reserve room for `n` more values for `pool`, destroying values idle in the other
+    /// pools if there isn't enough
+    pub(crate) fn reserve(&self, n: usize, pool: &PoolInner<T>) -> bool {
+        loop {
+            let reserved = self
+                .used
+                .fetch_update(AcqRel, Acquire, |used| {
+                    used.checked_add(n).filter(|&used| used <= self.max_size)
+                })
+                .is_ok();
+            if reserved {
+                return true;
+            }
+            if !self.evict(pool) {
+                return false;
+            }
+        }
+    }
+
+    /// Destroy a value idle in a pool other than `pool`, returning whether there was one
+    fn evict(&self, pool: &PoolInner<T>) -> bool {
+        for other in self.pools() {
+            if ptr::eq(&*other, pool) {
+                continue;
+            }
+            if let Some(entry) = other.take() {
+                other.destroy(entry.value, entry.origin.id);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Give back room for `n` values, letting the pools create new ones
+    pub(crate) fn release(&self, n: usize) {
+        if n > 0 {
+            self.used.fetch_sub(n, AcqRel);
+            self.wake();
+        }
+    }
+
+    /// Whether the pools together own as many values as they may
+    pub(crate) fn is_full(&self) -> bool {
+        self.used.load(Acquire) >= self.max_size
+    }
+
+    /// Wake a waiter in every pool, since any of them may be waiting for room
+    pub(crate) fn wake(&self) {
+        for pool in self.pools() {
+            pool.wake();
+        }
+    }
+}
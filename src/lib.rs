@@ -17,7 +17,7 @@
 //!    assert_eq!(pool.remaining_capacity(), 10);
 //!
 //!    // Get a value from the pool
-//!    let mut box1 = pool.acquire().await;
+//!    let mut box1 = pool.acquire().await.unwrap();
 //!    assert_eq!(pool.remaining_capacity(), 9);
 //!
 //!    // Use the value
@@ -40,11 +40,71 @@
 //!   fn foo(&mut self) { }
 //! }
 //! ```
-use crossbeam_queue::ArrayQueue;
+//!
+//! # Runtimes
+//!
+//! Waiting for a value only relies on [`Waker`](std::task::Waker)s, so pools work on any
+//! executor, such as `smol`, `async-std` or `futures::executor`. Only the default
+//! `time` and `rt` features need tokio: they add timeouts, run on tokio's timer, and
+//! `Pool::spawn_maintenance`, run as a tokio task. Turn off the default features to
+//! leave tokio out of the dependency tree:
+//!
+//! ```toml
+//! [dependencies]
+//! tub = { version = "0.4.0", default-features = false }
+//! ```
+mod batch;
+mod blocking;
+mod builder;
+mod check;
+mod error;
+mod export;
+mod histogram;
+mod hooks;
+mod keyed;
+mod local;
+mod manager;
+mod reset;
+mod sharded;
+mod stack;
+mod status;
+mod store;
+mod trace;
+mod wait;
+
+pub use batch::GuardSet;
+pub use builder::Builder;
+pub use error::AcquireError;
+pub use histogram::{Percentiles, PoolMetrics};
+pub use hooks::Metadata;
+pub use keyed::KeyedPool;
+pub use local::{LocalGuard, LocalPool};
+pub use manager::Manager;
+pub use reset::Reset;
+pub use sharded::ShardedPool;
+pub use status::PoolStatus;
+pub use store::QueueMode;
+pub use wait::Priority;
+
+use builder::Config;
+use export::Exporter;
+use histogram::Histogram;
+use keyed::Budget;
+use manager::Source;
+use status::Counters;
 use std::iter::Iterator;
+use std::mem;
 use std::ops::{Deref, DerefMut};
+use std::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed, Release};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize};
 use std::sync::Arc;
-use tokio::sync::Notify;
+#[cfg(feature = "rt")]
+use std::sync::Weak;
+use std::task::Poll;
+use std::time::Duration;
+use std::time::Instant;
+use store::Store;
+use wait::{poll_once, Place, Wait, WaitList};
 
 /// A shared resource pool
 ///
@@ -63,19 +123,83 @@ use tokio::sync::Notify;
 ///       .into();
 ///   
 ///   // Get a socket from the pool
-///   let mut socket = pool.acquire().await;
+///   let mut socket = pool.acquire().await.unwrap();
 /// }
 ///```
-#[derive(Clone)]
 pub struct Pool<T> {
     inner: Arc<PoolInner<T>>,
 }
 
+// Not derived, since a handle can be cloned whether or not the values can
+impl<T> Clone for Pool<T> {
+    fn clone(&self) -> Self {
+        Pool {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
 struct PoolInner<T> {
     /// The queue of idle resources
-    queue: ArrayQueue<T>,
-    /// Notify waiting tasks
-    notify: Notify,
+    queue: Store<Entry<T>>,
+    /// Tasks waiting for a value
+    waiters: WaitList<Entry<T>>,
+    /// The number of values owned by the pool, whether idle, in use, or being created
+    size: AtomicUsize,
+    /// The most values the pool may own at once
+    max_size: usize,
+    /// Creates values on demand, if the pool has an initializer or a [`Manager`]
+    source: Option<Source<T>>,
+    /// The id of the next value to enter the pool
+    next_id: AtomicU64,
+    /// Cumulative counters reported by [`Pool::status`]
+    counters: Counters,
+    /// Publishes the pool's state with the `metrics` feature
+    exporter: Exporter,
+    /// How long [`Pool::acquire`] waited for each [`Guard`]
+    wait_time: Histogram,
+    /// How long each [`Guard`] was held
+    hold_time: Histogram,
+    /// Set by [`Pool::close`] and [`Pool::drain`]
+    closed: AtomicBool,
+    /// Set by [`Pool::drain`] so that returned values are kept for it
+    draining: AtomicBool,
+    /// Settings from the [`Builder`], shared by the pools of a [`KeyedPool`]
+    config: Arc<Config<T>>,
+    /// Room shared with the other pools of a [`KeyedPool`]
+    budget: Option<Arc<Budget<T>>>,
+}
+
+/// Where a value came from, which stays with it for as long as the pool owns it
+#[derive(Clone, Copy)]
+struct Origin {
+    /// Identifies the value in [`Metadata`]
+    id: u64,
+    /// When the value was created
+    created: Instant,
+}
+
+/// An idle value in the queue
+struct Entry<T> {
+    value: T,
+    origin: Origin,
+    /// When the value was last returned to the pool, or created. Only kept up to date
+    /// when values can expire or be reaped, see [`PoolInner::put`].
+    returned: Instant,
+    /// Whether an asynchronous return check still has to run
+    unchecked: bool,
+}
+
+impl<T> Entry<T> {
+    /// Track a value that was just created
+    fn new(value: T, origin: Origin) -> Self {
+        Self {
+            value,
+            origin,
+            returned: origin.created,
+            unchecked: false,
+        }
+    }
 }
 
 /// A handle to a value from the pool
@@ -93,7 +217,7 @@ struct PoolInner<T> {
 ///   let pool: Pool<u32> = Pool::from_default(10);
 ///
 ///   // Get a value from the pool
-///   let mut value: Guard<u32> = pool.acquire().await;
+///   let mut value: Guard<u32> = pool.acquire().await.unwrap();
 ///   
 ///   // Return the value to the pool
 ///   drop(value);
@@ -103,14 +227,39 @@ pub struct Guard<T> {
     /// A value from the pool
     /// Option is used to play nicely with borrowing rules
     value: Option<T>,
+    origin: Origin,
+    /// When the value was handed out, if anything needs to know how long it is held
+    acquired: Option<Instant>,
     /// A reference to the pool used to return the value when dropped
     inner: Arc<PoolInner<T>>,
 }
 
 impl<T> Pool<T> {
+    /// Create a [`Builder`] to configure a new pool
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    /// let pool = Pool::builder().build(0..10);
+    /// assert_eq!(pool.remaining_capacity(), 10);
+    /// ```
+    pub fn builder() -> Builder<T> {
+        Builder::new()
+    }
+
     /// Acquire a value from the pool.
     ///
-    /// The value is protected by a [`Guard`]
+    /// The value is protected by a [`Guard`]. If the pool was built with a
+    /// `Builder::wait_timeout`, waiting longer than that fails with [`AcquireError::Timeout`].
+    ///
+    /// If the pool has a [`Manager`], idle values are recycled before they are handed out,
+    /// and new values are created while the pool is below its maximum size.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AcquireError::Exhausted`] if the pool has no values and can't create any,
+    /// [`AcquireError::Create`] if the [`Manager`] failed to create a value, or
+    /// [`AcquireError::Closed`] if the pool is closed.
     ///
     /// # Examples
     /// ```
@@ -119,24 +268,241 @@ impl<T> Pool<T> {
     /// #[tokio::main]
     /// async fn main() {
     ///    let pool: Pool<u32> = Pool::from_default(10);
-    ///    let mut box1 = pool.acquire().await;
+    ///    let mut box1 = pool.acquire().await.unwrap();
     ///    assert_eq!(pool.remaining_capacity(), 9);
     ///    assert_eq!(*box1, u32::default());
     /// }
     /// ```
     #[inline]
-    pub async fn acquire(&self) -> Guard<T> {
-        let inner = self.inner.clone();
-        loop {
-            if let Some(value) = inner.queue.pop() {
-                return Guard {
-                    value: Some(value),
-                    inner,
-                };
+    pub async fn acquire(&self) -> Result<Guard<T>, AcquireError> {
+        self.acquire_with_priority(Priority::Normal).await
+    }
+
+    /// Acquire a value from the pool like [`Pool::acquire`], ahead of or behind other
+    /// waiting tasks.
+    ///
+    /// A returned value goes to the waiter with the highest priority, and among those
+    /// to the one that has waited longest. So that a steady stream of high priority
+    /// tasks can't starve the others, waiters gain a priority for every
+    /// [`Builder::priority_aging`] they have waited.
+    ///
+    /// Priorities only decide the order among waiting tasks. In a pool that isn't
+    /// [fair](Builder::fair), a task that finds an idle value takes it whatever its priority.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`Pool::acquire`].
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::{Pool, Priority};
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() {
+    ///    let pool = Pool::from_vec(vec![1]);
+    ///    let value = pool.acquire().await.unwrap();
+    ///
+    ///    let bulk = tokio::spawn({
+    ///        let pool = pool.clone();
+    ///        async move { pool.acquire_with_priority(Priority::Low).await.map(|_| "bulk") }
+    ///    });
+    ///    let health = tokio::spawn({
+    ///        let pool = pool.clone();
+    ///        async move { pool.acquire_with_priority(Priority::High).await.map(|_| "health") }
+    ///    });
+    ///    tokio::task::yield_now().await;
+    ///
+    ///    // The health check started waiting last, but is served first
+    ///    drop(value);
+    ///    assert_eq!(health.await.unwrap().unwrap(), "health");
+    ///    assert_eq!(bulk.await.unwrap().unwrap(), "bulk");
+    /// }
+    /// ```
+    pub async fn acquire_with_priority(
+        &self,
+        priority: Priority,
+    ) -> Result<Guard<T>, AcquireError> {
+        #[cfg(feature = "time")]
+        if let Some(timeout) = self.inner.config.wait_timeout {
+            return match tokio::time::timeout(timeout, self.wait(priority)).await {
+                Ok(result) => result,
+                Err(_) => Err(self.inner.timed_out()),
+            };
+        }
+
+        self.wait(priority).await
+    }
+
+    /// Acquire `n` values from the pool at once, like [`Pool::acquire`].
+    ///
+    /// The values are taken all together or not at all: while waiting, the caller holds
+    /// on to none of them, so tasks that each need several values can't deadlock by
+    /// holding part of what another needs. Values returned while the caller waits are
+    /// set aside for it, so it is not overtaken by tasks that need fewer.
+    ///
+    /// The values are protected by a [`GuardSet`], which returns all of them when dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`Pool::acquire`], and [`AcquireError::Exhausted`] if
+    /// the pool can never hold `n` values.
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///    let pool = Pool::from_vec(vec![1, 2, 3]);
+    ///    let values = pool.acquire_many(2).await.unwrap();
+    ///    assert_eq!(values.len(), 2);
+    ///    assert_eq!(pool.remaining_capacity(), 1);
+    /// }
+    /// ```
+    pub async fn acquire_many(&self, n: usize) -> Result<GuardSet<T>, AcquireError> {
+        #[cfg(feature = "time")]
+        if let Some(timeout) = self.inner.config.wait_timeout {
+            return match tokio::time::timeout(timeout, self.wait_many(n, Priority::Normal, None))
+                .await
+            {
+                Ok(result) => result,
+                Err(_) => Err(self.inner.timed_out()),
+            };
+        }
+
+        self.wait_many(n, Priority::Normal, None).await
+    }
+
+    /// Acquire a value from the pool, waiting at most `timeout`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AcquireError::Timeout`] if no value became available in time.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use tub::{AcquireError, Pool};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///    let pool: Pool<u32> = Pool::from_default(1);
+    ///    let value = pool.acquire_timeout(Duration::from_secs(1)).await.unwrap();
+    ///
+    ///    let result = pool.acquire_timeout(Duration::from_millis(10)).await;
+    ///    assert!(matches!(result, Err(AcquireError::Timeout)));
+    /// }
+    /// ```
+    #[cfg(feature = "time")]
+    pub async fn acquire_timeout(&self, timeout: Duration) -> Result<Guard<T>, AcquireError> {
+        match tokio::time::timeout(timeout, self.wait(Priority::Normal)).await {
+            Ok(result) => result,
+            Err(_) => Err(self.inner.timed_out()),
+        }
+    }
+
+    /// Acquire a value from the pool, waiting until `deadline` at the latest.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AcquireError::Timeout`] if no value became available before the deadline.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::{Duration, Instant};
+    /// use tub::{AcquireError, Pool};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///    let pool: Pool<u32> = Pool::from_default(1);
+    ///    let deadline = Instant::now() + Duration::from_millis(10);
+    ///    let value = pool.acquire_until(deadline).await.unwrap();
+    ///
+    ///    let result = pool.acquire_until(deadline).await;
+    ///    assert!(matches!(result, Err(AcquireError::Timeout)));
+    /// }
+    /// ```
+    #[cfg(feature = "time")]
+    pub async fn acquire_until(&self, deadline: Instant) -> Result<Guard<T>, AcquireError> {
+        match tokio::time::timeout_at(deadline.into(), self.wait(Priority::Normal)).await {
+            Ok(result) => result,
+            Err(_) => Err(self.inner.timed_out()),
+        }
+    }
+
+    /// Wait until a value is available, without a deadline
+    async fn wait(&self, priority: Priority) -> Result<Guard<T>, AcquireError> {
+        let set = self.wait_many(1, priority, None).await?;
+        Ok(set.into_guard())
+    }
+
+    /// Wrap a value taken from the pool in a [`Guard`], after waiting `waited` for it
+    fn guard(&self, value: T, origin: Origin, waited: Duration) -> Guard<T> {
+        self.inner.begin_use(&value, origin.id, waited);
+        Guard {
+            value: Some(value),
+            origin,
+            acquired: self.inner.hold_start(),
+            inner: self.inner.clone(),
+        }
+    }
+
+    /// Try to acquire a value from the pool without waiting.
+    ///
+    /// Returns [`None`] if the pool is empty or closed, or if the pool is
+    /// [fair](Builder::fair) and other tasks are waiting. Unlike [`Pool::acquire`],
+    /// this never registers the caller as a waiter, so it is safe to use as a fast path
+    /// before falling back to some other resource.
+    ///
+    /// Only idle values are returned: this never creates a value with the pool's
+    /// [`Manager`]. [`Manager::recycle`] and the checks from
+    /// [`Builder::test_on_acquire_async`] and [`Builder::test_on_return_async`] are polled
+    /// once, and a value whose recycling or checks aren't done by then is left for
+    /// [`Pool::acquire`].
+    ///
+    /// The returned [`Guard`] owns a reference to the pool and does not borrow it,
+    /// so it can be moved into spawned tasks as-is.
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    ///
+    /// let pool: Pool<u32> = Pool::from_default(1);
+    /// let value = pool.try_acquire().unwrap();
+    /// assert!(pool.try_acquire().is_none());
+    ///
+    /// drop(value);
+    /// assert!(pool.try_acquire().is_some());
+    /// ```
+    #[inline]
+    pub fn try_acquire(&self) -> Option<Guard<T>> {
+        if self.inner.is_closed() || !self.inner.waiters.may_skip_line() {
+            return None;
+        }
+
+        // Values that aren't ready when first polled are left for `acquire`
+        let mut pending = Vec::new();
+        let mut guard = None;
+        while let Some(mut entry) = self.inner.pop() {
+            let origin = entry.origin;
+            match poll_once(self.inner.ready(&mut entry.value, entry.unchecked)) {
+                Poll::Ready(true) => {
+                    guard = Some(self.guard(entry.value, origin, Duration::ZERO));
+                    break;
+                }
+                Poll::Ready(false) => self.inner.destroy(entry.value, origin.id),
+                Poll::Pending => pending.push(entry),
             }
+        }
 
-            inner.notify.notified().await;
+        // Put the values back in the order they came out, as in `reap`
+        if self.inner.queue.is_lifo() {
+            pending.reverse();
+        }
+        for entry in pending {
+            self.inner.push(entry);
         }
+        guard
     }
 
     /// Get the number of available values in the pool
@@ -151,31 +517,241 @@ impl<T> Pool<T> {
         self.inner.queue.len()
     }
 
-    /// Create a new pool from a vector of values
+    /// Add a value to the pool.
+    ///
+    /// # Errors
+    ///
+    /// Gives the value back if the pool is closed or already owns its
+    /// [maximum number of values](Builder::max_size).
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    /// let pool = Pool::from_vec(vec![1, 2]);
+    /// pool.add(3).unwrap();
+    /// assert_eq!(pool.remaining_capacity(), 3);
+    ///
+    /// let full = Pool::builder().max_size(1).build(vec![1]);
+    /// assert_eq!(full.add(2), Err(2));
+    /// ```
+    pub fn add(&self, value: T) -> Result<(), T> {
+        if self.inner.is_closed() || !self.inner.reserve(1) {
+            return Err(value);
+        }
+
+        let origin = self.inner.origin();
+        self.inner.push(Entry::new(value, origin));
+        Ok(())
+    }
+
+    /// Take up to `n` idle values out of the pool for good.
+    ///
+    /// Values in use are left alone, so fewer than `n` values may be returned.
+    /// Like [`Guard::detach`], this makes room for a pool with an initializer or a
+    /// [`Manager`] to create replacements.
     ///
     /// # Examples
     /// ```
     /// use tub::Pool;
     /// let pool = Pool::from_vec(vec![1, 2, 3]);
+    /// assert_eq!(pool.remove_idle(2), vec![1, 2]);
+    /// assert_eq!(pool.status().size, 1);
     /// ```
-    pub fn from_vec(vec: Vec<T>) -> Self {
-        let queue = ArrayQueue::new(vec.len());
+    pub fn remove_idle(&self, n: usize) -> Vec<T> {
+        let mut removed = Vec::new();
+        while removed.len() < n {
+            match self.inner.pop() {
+                Some(entry) => removed.push(entry.value),
+                None => break,
+            }
+        }
 
-        for item in vec {
-            let _ = queue.push(item);
+        for _ in 0..removed.len() {
+            self.inner.release();
         }
 
-        Self {
-            inner: Arc::new(PoolInner {
-                queue,
-                notify: Notify::new(),
-            }),
+        removed
+    }
+
+    /// Close the pool.
+    ///
+    /// Pending and future calls to [`Pool::acquire`] fail with [`AcquireError::Closed`]
+    /// and idle values are destroyed. Values in use are destroyed when their [`Guard`]
+    /// is dropped, instead of going back into the pool.
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::{AcquireError, Pool};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///    let pool = Pool::from_vec(vec![1, 2]);
+    ///    let value = pool.acquire().await.unwrap();
+    ///
+    ///    pool.close();
+    ///    assert!(matches!(pool.acquire().await, Err(AcquireError::Closed)));
+    ///
+    ///    drop(value);
+    ///    assert_eq!(pool.status().size, 0);
+    /// }
+    /// ```
+    pub fn close(&self) {
+        self.inner.closed.store(true, Release);
+        self.inner.waiters.wake_all();
+        self.inner.clear();
+    }
+
+    /// Whether the pool has been closed by [`Pool::close`] or [`Pool::drain`]
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    /// let pool = Pool::from_vec(vec![1]);
+    /// assert!(!pool.is_closed());
+    ///
+    /// pool.close();
+    /// assert!(pool.is_closed());
+    /// ```
+    pub fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+
+    /// Close the pool and wait for every value to come back, then return them.
+    ///
+    /// Like [`Pool::close`], pending and future calls to [`Pool::acquire`] fail with
+    /// [`AcquireError::Closed`]. Values that were already destroyed, e.g. by an earlier
+    /// call to [`Pool::close`], are not recovered.
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///    let pool = Pool::from_vec(vec![1, 2]);
+    ///    let value = pool.acquire().await.unwrap();
+    ///
+    ///    tokio::spawn(async move {
+    ///        // Finish with the value, then return it
+    ///        drop(value);
+    ///    });
+    ///
+    ///    let mut values = pool.drain().await;
+    ///    values.sort();
+    ///    assert_eq!(values, vec![1, 2]);
+    /// }
+    /// ```
+    pub async fn drain(&self) -> Vec<T> {
+        let inner = &self.inner;
+        inner.draining.store(true, Release);
+        inner.closed.store(true, Release);
+        inner.waiters.wake_all();
+
+        // Joined before looking at the pool so that a returned value can't be missed
+        let line = InLine::join(inner, Priority::Normal, 1, Instant::now());
+        let mut values = Vec::new();
+        loop {
+            // A value may have been handed to us before the pool was closed
+            while let Some(entry) = line.take().or_else(|| inner.take()) {
+                values.push(entry.value);
+                inner.forget(1);
+            }
+
+            if inner.size.load(Acquire) == 0 {
+                return values;
+            }
+
+            line.wait().await;
         }
     }
 
+    /// Get the pool's name, see [`Builder::name`]
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    /// let pool = Pool::from_vec(vec![1]);
+    /// assert_eq!(pool.name(), "tub");
+    /// ```
+    pub fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    /// Get a snapshot of the pool's state
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    /// let pool = Pool::from_iter(0..10);
+    /// let status = pool.status();
+    /// assert_eq!(status.size, 10);
+    /// assert_eq!(status.idle, 10);
+    /// ```
+    pub fn status(&self) -> PoolStatus {
+        let inner = &self.inner;
+        let counters = &inner.counters;
+        // Releases are read first, so that each of them has its acquire counted too
+        let releases = counters.releases.load(Acquire);
+        let acquires = counters.acquires.load(Acquire);
+        PoolStatus {
+            size: inner.size.load(Relaxed),
+            idle: inner.queue.len(),
+            in_use: acquires.saturating_sub(releases),
+            waiters: counters.waiters.load(Relaxed),
+            acquires,
+            releases,
+            timeouts: counters.timeouts.load(Relaxed),
+            created: counters.created.load(Relaxed),
+            destroyed: counters.destroyed.load(Relaxed),
+            failed_checks: counters.failed_checks.load(Relaxed),
+        }
+    }
+
+    /// Get percentiles of how long [`Pool::acquire`] waits and how long [`Guard`]s are held
+    ///
+    /// The times are only recorded for pools built with [`Builder::record_timings`].
+    /// Only acquires that returned a [`Guard`] are counted, and a [`Guard`] is counted
+    /// once it is dropped or detached.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use tub::Pool;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///    let pool = Pool::builder().record_timings(true).build(vec![1]);
+    ///    let value = pool.acquire().await.unwrap();
+    ///    tokio::time::sleep(Duration::from_millis(10)).await;
+    ///    drop(value);
+    ///
+    ///    let metrics = pool.metrics();
+    ///    assert!(metrics.hold.max >= Duration::from_millis(10));
+    ///    assert!(metrics.wait.p99 < Duration::from_millis(10));
+    /// }
+    /// ```
+    pub fn metrics(&self) -> PoolMetrics {
+        PoolMetrics {
+            wait: self.inner.wait_time.percentiles(),
+            hold: self.inner.hold_time.percentiles(),
+        }
+    }
+
+    /// Create a new pool from a vector of values
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    /// let pool = Pool::from_vec(vec![1, 2, 3]);
+    /// ```
+    pub fn from_vec(vec: Vec<T>) -> Self {
+        Builder::new().build(vec)
+    }
+
     /// Create a new pool from an initializer.
     ///
-    /// The initializer is called once for each value in the pool.
+    /// The initializer is called once for each value in the pool. Use
+    /// [`Builder::build_with_initializer`] for a pool that also replaces values with it.
     ///
     /// # Examples
     /// ```
@@ -191,34 +767,681 @@ impl<T> Pool<T> {
     where
         F: Fn() -> T,
     {
-        let queue = ArrayQueue::new(capacity);
+        Builder::new()
+            .max_size(capacity)
+            .build((0..capacity).map(|_| init()))
+    }
 
-        for _ in 0..capacity {
-            // Safety: The queue can hold every item we push
-            let _ = queue.push(init());
+    /// Create a new pool from an iterator
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    /// let pool = Pool::from_iter(0..10);
+    /// ```
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_iter<I>(iterable: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+    {
+        Pool::from_vec(iterable.into_iter().collect())
+    }
+
+    /// Destroy values that have been idle for longer than the
+    /// [idle timeout](Builder::idle_timeout) or have outlived the
+    /// [maximum lifetime](Builder::max_lifetime), then create values until the pool has
+    /// [`min_idle`](Builder::min_idle) idle values or reaches its maximum size.
+    ///
+    /// This doesn't need a particular runtime. With the `rt` feature,
+    /// `Pool::spawn_maintenance` runs it periodically on a tokio task.
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::Pool;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///    let pool = Pool::builder()
+    ///        .min_idle(4)
+    ///        .max_size(8)
+    ///        .build_with_initializer(0, || 0);
+    ///    assert_eq!(pool.remaining_capacity(), 0);
+    ///
+    ///    pool.maintain().await;
+    ///    assert_eq!(pool.remaining_capacity(), 4);
+    /// }
+    /// ```
+    pub async fn maintain(&self) {
+        self.inner.reap();
+        if !self.inner.is_closed() {
+            self.inner.replenish().await;
         }
+    }
 
+    /// Create a pool that may own up to `max_size` values and starts with `values`
+    fn from_parts(
+        max_size: usize,
+        values: Vec<T>,
+        source: Option<Source<T>>,
+        config: Arc<Config<T>>,
+        budget: Option<Arc<Budget<T>>>,
+    ) -> Self {
+        let size = values.len();
+        let queue = Store::new(size, max_size, config.queue_mode);
+        let now = Instant::now();
+
+        // Values from an initializer were just created, unlike values given to the builder
+        let created = matches!(source, Some(Source::Initializer(_)));
+        for (id, item) in (0..).zip(values) {
+            if created {
+                hooks::run(&config.hooks.on_create, &item, Metadata::new(id));
+            }
+            queue.push(Entry::new(item, Origin { id, created: now }));
+        }
+
+        let exporter = Exporter::new(config.name(), size);
         Self {
             inner: Arc::new(PoolInner {
                 queue,
-                notify: Notify::new(),
+                waiters: WaitList::new(config.fair, config.priority_aging),
+                size: AtomicUsize::new(size),
+                max_size,
+                source,
+                next_id: AtomicU64::new(size as u64),
+                counters: Counters::default(),
+                exporter,
+                wait_time: Histogram::new(),
+                hold_time: Histogram::new(),
+                closed: AtomicBool::new(false),
+                draining: AtomicBool::new(false),
+                config,
+                budget,
             }),
         }
     }
+}
 
-    /// Create a new pool from an iterator
+#[cfg(feature = "rt")]
+impl<T: Send + 'static> Pool<T> {
+    /// Spawn a tokio task that calls [`Pool::maintain`] every `interval`.
+    ///
+    /// The task stops on its own once every handle to the pool has been dropped.
     ///
     /// # Examples
     /// ```
+    /// use std::time::Duration;
     /// use tub::Pool;
-    /// let pool = Pool::from_iter(0..10);
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///    let pool = Pool::builder()
+    ///        .idle_timeout(Duration::from_secs(60))
+    ///        .build(0..10);
+    ///    pool.spawn_maintenance(Duration::from_secs(30));
+    /// }
     /// ```
-    #[allow(clippy::should_implement_trait)]
-    pub fn from_iter<I>(iterable: I) -> Self
+    pub fn spawn_maintenance(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let inner: Weak<PoolInner<T>> = Arc::downgrade(&self.inner);
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            loop {
+                ticks.tick().await;
+                match inner.upgrade() {
+                    Some(inner) => Pool { inner }.maintain().await,
+                    None => return,
+                }
+            }
+        })
+    }
+}
+
+impl<T: Send + 'static> Pool<T> {
+    /// Create an empty pool whose values are created on demand by a [`Manager`]
+    ///
+    /// The pool never owns more than `max_size` values at once.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::convert::Infallible;
+    /// use tub::{Manager, Pool};
+    ///
+    /// struct Numbers;
+    ///
+    /// impl Manager for Numbers {
+    ///     type Type = u32;
+    ///     type Error = Infallible;
+    ///
+    ///     async fn create(&self) -> Result<u32, Infallible> {
+    ///         Ok(42)
+    ///     }
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let pool = Pool::with_manager(Numbers, 2);
+    ///     let value = pool.acquire().await.unwrap();
+    ///     assert_eq!(*value, 42);
+    /// }
+    /// ```
+    pub fn with_manager<M>(manager: M, max_size: usize) -> Self
     where
-        I: IntoIterator<Item = T>,
+        M: Manager<Type = T>,
     {
-        Pool::from_vec(iterable.into_iter().collect())
+        Builder::new().build_with_manager(manager, max_size)
+    }
+}
+
+impl<T> PoolInner<T> {
+    /// Reserve room for a new value, if the pool can create values and isn't full
+    fn try_reserve(&self) -> bool {
+        self.try_reserve_many(1)
+    }
+
+    /// Reserve room for `n` new values, if the pool can create values and has room
+    /// for all of them
+    fn try_reserve_many(&self, n: usize) -> bool {
+        self.source.is_some() && self.reserve(n)
+    }
+
+    /// Reserve room for `n` more values, if the pool has room for all of them
+    fn reserve(&self, n: usize) -> bool {
+        let reserved = self
+            .size
+            .fetch_update(AcqRel, Acquire, |size| {
+                size.checked_add(n).filter(|&size| size <= self.max_size)
+            })
+            .is_ok();
+
+        match &self.budget {
+            Some(budget) if reserved && !budget.reserve(n, self) => {
+                self.size.fetch_sub(n, AcqRel);
+                false
+            }
+            _ => reserved,
+        }
+    }
+
+    /// The pool's name, see [`Builder::name`]
+    fn name(&self) -> &str {
+        self.config.name()
+    }
+
+    /// Whether [`Pool::close`] or [`Pool::drain`] has been called
+    fn is_closed(&self) -> bool {
+        self.closed.load(Acquire)
+    }
+
+    /// Whether returned values should be destroyed, because the pool is closed
+    /// and not being drained
+    fn is_discarding(&self) -> bool {
+        self.is_closed() && !self.draining.load(Acquire)
+    }
+
+    /// Destroy every idle value
+    fn clear(&self) {
+        while let Some(entry) = self.take() {
+            self.destroy(entry.value, entry.origin.id);
+        }
+    }
+
+    /// Wake waiters after a value came back or room was made.
+    ///
+    /// Once the pool is closed every waiter is woken, so that acquirers can fail
+    /// and [`Pool::drain`] can collect the value.
+    fn wake(&self) {
+        if self.is_closed() {
+            self.waiters.wake_all();
+        } else {
+            self.waiters.wake_one();
+        }
+    }
+
+    /// Whether waiting can never produce `n` values
+    fn is_exhausted(&self, n: usize) -> bool {
+        let over_budget = matches!(&self.budget, Some(budget) if n > budget.max_size());
+        over_budget || n > self.max_size || (self.source.is_none() && self.size.load(Acquire) < n)
+    }
+
+    /// Create a value in room reserved by [`PoolInner::try_reserve`]
+    async fn create(&self) -> Result<(T, Origin), AcquireError> {
+        let mut slot = Slot {
+            inner: self,
+            value: None,
+            origin: self.origin(),
+        };
+
+        if let Some(source) = &self.source {
+            let value = source.create().await.map_err(|error| {
+                trace::event!(warn, pool = self.name(), %error, "failed to create a pooled value");
+                self.exporter.create_failed();
+                AcquireError::Create(error)
+            })?;
+            trace::event!(debug, pool = self.name(), "created a pooled value");
+            self.counters.created.fetch_add(1, Relaxed);
+            slot.origin.created = Instant::now();
+            let metadata = Metadata::new(slot.origin.id);
+            hooks::run(&self.config.hooks.on_create, &value, metadata);
+            slot.value = Some(value);
+        }
+
+        let origin = slot.origin;
+        let value = slot.keep().ok_or(AcquireError::Exhausted)?;
+        Ok((value, origin))
+    }
+
+    /// Get an idle value ready to be handed out.
+    ///
+    /// This runs a pending asynchronous return check, lets the manager recycle the value,
+    /// and runs the acquire check. If any of them fails, the value is destroyed.
+    async fn prepare(&self, entry: Entry<T>) -> Option<T> {
+        let recycles = matches!(self.source, Some(Source::Manager(_)));
+        if !entry.unchecked && !recycles && self.config.test_on_acquire.is_none() {
+            return Some(entry.value);
+        }
+
+        // The slot destroys the value if this future is cancelled part way through
+        let mut slot = Slot {
+            inner: self,
+            value: Some(entry.value),
+            origin: entry.origin,
+        };
+        // Safety: The slot was just filled
+        let value = slot.value.as_mut().unwrap();
+
+        match self.ready(value, entry.unchecked).await {
+            true => slot.keep(),
+            false => None,
+        }
+    }
+
+    /// Run the checks of [`PoolInner::prepare`] on `value`, counting a failure.
+    ///
+    /// The caller has to destroy the value if this returns false.
+    async fn ready(&self, value: &mut T, unchecked: bool) -> bool {
+        if unchecked {
+            if let Some(check) = &self.config.test_on_return {
+                if !check.run(value).await {
+                    self.counters.failed_checks.fetch_add(1, Relaxed);
+                    return false;
+                }
+            }
+        }
+
+        // Initializers have nothing to recycle
+        if let Some(source @ Source::Manager(_)) = &self.source {
+            if !source.recycle(value).await {
+                trace::event!(
+                    debug,
+                    pool = self.name(),
+                    "failed to recycle a pooled value"
+                );
+                self.counters.failed_checks.fetch_add(1, Relaxed);
+                return false;
+            }
+            trace::event!(trace, pool = self.name(), "recycled a pooled value");
+        }
+
+        if let Some(check) = &self.config.test_on_acquire {
+            if !check.run(value).await {
+                self.counters.failed_checks.fetch_add(1, Relaxed);
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Whether a value created at `created` has outlived the maximum lifetime
+    fn is_expired(&self, created: Instant, now: Instant) -> bool {
+        match self.config.max_lifetime {
+            Some(lifetime) => now.saturating_duration_since(created) >= lifetime,
+            None => false,
+        }
+    }
+
+    /// Take an idle value out of the queue, destroying any that have expired on the way
+    fn pop(&self) -> Option<Entry<T>> {
+        while let Some(entry) = self.take() {
+            // Skip reading the clock when values never expire
+            let expired = self.config.max_lifetime.is_some()
+                && self.is_expired(entry.origin.created, Instant::now());
+            if expired {
+                self.destroy(entry.value, entry.origin.id);
+            } else {
+                return Some(entry);
+            }
+        }
+
+        None
+    }
+
+    /// Return a value from a [`Guard`], destroying it if the pool is closed, or if it
+    /// has expired or fails the return check
+    fn put(&self, mut value: T, origin: Origin) {
+        // Only values that can expire or be reaped need to know when they came back
+        let now = match self.config.idle_timeout.or(self.config.max_lifetime) {
+            Some(_) => Instant::now(),
+            None => origin.created,
+        };
+        if self.is_discarding() || self.is_expired(origin.created, now) {
+            return self.destroy(value, origin.id);
+        }
+
+        if let Some(reset) = &self.config.reset {
+            reset(&mut value);
+        }
+
+        // Asynchronous checks can't run here, so they are left for `prepare`
+        let unchecked = match &self.config.test_on_return {
+            Some(check) => match check.run_sync(&mut value) {
+                Some(true) => false,
+                Some(false) => return self.reject(value, origin.id),
+                None => true,
+            },
+            None => false,
+        };
+
+        self.push(Entry {
+            value,
+            origin,
+            returned: now,
+            unchecked,
+        });
+    }
+
+    /// Hand a value to the longest waiting task, or put it back into the queue and
+    /// wake a waiter if no task is waiting
+    fn push(&self, entry: Entry<T>) {
+        // Handing the value over means a task that hasn't waited can't take it first
+        let entry = match self.is_closed() {
+            true => entry,
+            false => match self.waiters.hand_off(entry) {
+                Ok(()) => return,
+                Err(entry) => entry,
+            },
+        };
+
+        self.queue.push(entry);
+        self.exporter.idle(1);
+
+        // Another pool sharing the budget may be waiting to make room from this value
+        if let Some(budget) = &self.budget {
+            if budget.is_full() {
+                budget.wake();
+            }
+        }
+
+        // The pool may have been closed since the caller last checked
+        if self.is_discarding() {
+            self.clear();
+        }
+
+        self.wake();
+    }
+
+    /// Take the next idle value out of the queue, whether or not it has expired
+    fn take(&self) -> Option<Entry<T>> {
+        let entry = self.queue.pop()?;
+        self.exporter.idle(-1);
+        Some(entry)
+    }
+
+    /// Permanently remove the value with the given `id` from the pool
+    fn destroy(&self, value: T, id: u64) {
+        trace::event!(debug, pool = self.name(), "destroyed a pooled value");
+        self.counters.destroyed.fetch_add(1, Relaxed);
+        hooks::run(&self.config.hooks.on_destroy, &value, Metadata::new(id));
+        match &self.source {
+            Some(source) => source.detach(value),
+            None => drop(value),
+        }
+
+        self.release();
+    }
+
+    /// Destroy a value that failed a check
+    fn reject(&self, value: T, id: u64) {
+        self.counters.failed_checks.fetch_add(1, Relaxed);
+        self.destroy(value, id);
+    }
+
+    /// Destroy the values that have been idle for longer than the idle timeout,
+    /// or that have outlived the maximum lifetime
+    fn reap(&self) {
+        if self.config.idle_timeout.is_none() && self.config.max_lifetime.is_none() {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut kept = Vec::new();
+        let mut expired = Vec::new();
+        for _ in 0..self.queue.len() {
+            let entry = match self.take() {
+                Some(entry) => entry,
+                None => break,
+            };
+
+            let idle = match self.config.idle_timeout {
+                Some(timeout) => now.saturating_duration_since(entry.returned) >= timeout,
+                None => false,
+            };
+
+            if idle || self.is_expired(entry.origin.created, now) {
+                expired.push(entry);
+            } else {
+                kept.push(entry);
+            }
+        }
+
+        // Put the values back in the order they came out, which for a stack means the
+        // last one out goes in first
+        if self.queue.is_lifo() {
+            kept.reverse();
+        }
+        for entry in kept {
+            self.push(entry);
+        }
+
+        // Destroying runs user code, so it waits until the kept values can be acquired
+        for entry in expired {
+            self.destroy(entry.value, entry.origin.id);
+        }
+    }
+
+    /// Create idle values until there are `min_idle` of them or the pool is full
+    async fn replenish(&self) {
+        while self.queue.len() < self.config.min_idle && self.try_reserve() {
+            match self.create().await {
+                Ok((value, origin)) => self.push(Entry::new(value, origin)),
+                Err(_) => break,
+            }
+        }
+    }
+
+    /// Count a value as handed out, after waiting `waited` for it
+    fn begin_use(&self, value: &T, id: u64, waited: Duration) {
+        self.counters.acquires.fetch_add(1, Release);
+        self.exporter.acquired();
+        self.exporter.in_use(1);
+
+        let metadata = Metadata {
+            waited: Some(waited),
+            ..Metadata::new(id)
+        };
+        hooks::run(&self.config.hooks.on_acquire, value, metadata);
+    }
+
+    /// When a value handed out now starts being held, if the hold time is recorded or
+    /// passed to a hook
+    fn hold_start(&self) -> Option<Instant> {
+        let timed = self.config.record_timings || self.config.hooks.on_release.is_some();
+        timed.then(Instant::now)
+    }
+
+    /// Count a [`Guard`] acquired at `acquired` as dropped or detached
+    fn end_use(&self, value: &T, id: u64, acquired: Option<Instant>) {
+        let held = acquired.map(|acquired| acquired.elapsed());
+        self.counters.releases.fetch_add(1, Release);
+        self.exporter.in_use(-1);
+        if let (true, Some(held)) = (self.config.record_timings, held) {
+            self.hold_time.record(held);
+        }
+
+        let metadata = Metadata {
+            held,
+            ..Metadata::new(id)
+        };
+        hooks::run(&self.config.hooks.on_release, value, metadata);
+    }
+
+    /// Give a new value its [`Origin`]
+    fn origin(&self) -> Origin {
+        Origin {
+            id: self.next_id.fetch_add(1, Relaxed),
+            created: Instant::now(),
+        }
+    }
+
+    /// Count an acquire that gave up waiting
+    fn timed_out(&self) -> AcquireError {
+        self.counters.timeouts.fetch_add(1, Relaxed);
+        self.exporter.timed_out();
+        AcquireError::Timeout
+    }
+
+    /// Give up room in the pool, letting a waiter create a new value
+    fn release(&self) {
+        self.forget(1);
+        self.wake();
+    }
+
+    /// Stop counting `n` values that have left the pool
+    fn forget(&self, n: usize) {
+        self.size.fetch_sub(n, AcqRel);
+        if let Some(budget) = &self.budget {
+            budget.release(n);
+        }
+    }
+}
+
+impl<T> Drop for PoolInner<T> {
+    fn drop(&mut self) {
+        // Another pool with the same name may still report to the gauge
+        self.exporter.idle(-(self.queue.len() as isize));
+
+        if let Some(source) = &self.source {
+            while let Some(entry) = self.queue.pop() {
+                source.detach(entry.value);
+            }
+        }
+
+        // Nothing can be in use anymore, so every value the pool owned was idle
+        if let Some(budget) = &self.budget {
+            budget.release(*self.size.get_mut());
+        }
+    }
+}
+
+/// Room in the pool for a value that is neither idle nor held by a [`Guard`]
+///
+/// If the [`Slot`] is dropped, e.g. because the future using it was cancelled,
+/// its value is destroyed and the room is given back.
+struct Slot<'a, T> {
+    inner: &'a PoolInner<T>,
+    value: Option<T>,
+    origin: Origin,
+}
+
+impl<T> Slot<'_, T> {
+    /// Keep the value, which stays counted in the pool's size
+    fn keep(mut self) -> Option<T> {
+        let value = self.value.take();
+        if value.is_some() {
+            mem::forget(self);
+        }
+        value
+    }
+}
+
+impl<T> Drop for Slot<'_, T> {
+    fn drop(&mut self) {
+        match self.value.take() {
+            Some(value) => self.inner.destroy(value, self.origin.id),
+            None => self.inner.release(),
+        }
+    }
+}
+
+/// Counts a task as a waiter for as long as it lives, so that cancelled waits
+/// are not counted forever
+struct Waiting<'a, T> {
+    inner: &'a PoolInner<T>,
+}
+
+impl<'a, T> Waiting<'a, T> {
+    fn new(inner: &'a PoolInner<T>) -> Self {
+        inner.counters.waiters.fetch_add(1, Relaxed);
+        inner.exporter.waiters(1);
+        Waiting { inner }
+    }
+}
+
+impl<T> Drop for Waiting<'_, T> {
+    fn drop(&mut self) {
+        self.inner.counters.waiters.fetch_sub(1, Relaxed);
+        self.inner.exporter.waiters(-1);
+    }
+}
+
+/// A task's place in line, which gives away the values handed to the task if it leaves
+/// without taking them
+struct InLine<'a, T> {
+    inner: &'a PoolInner<T>,
+    /// Only empty while being dropped
+    place: Option<Place<'a, Entry<T>>>,
+}
+
+impl<'a, T> InLine<'a, T> {
+    /// Get in line for `wants` values at time `joined`
+    fn join(inner: &'a PoolInner<T>, priority: Priority, wants: usize, joined: Instant) -> Self {
+        InLine {
+            inner,
+            place: Some(inner.waiters.join(priority, wants, joined)),
+        }
+    }
+
+    fn place(&self) -> &Place<'a, Entry<T>> {
+        // Safety: The place is only taken when dropped
+        self.place.as_ref().unwrap()
+    }
+
+    fn may_take(&self) -> bool {
+        self.place().may_take()
+    }
+
+    /// Take a value handed to this task, if any
+    fn take(&self) -> Option<Entry<T>> {
+        self.place().take()
+    }
+
+    fn wait(&self) -> Wait<'_, 'a, Entry<T>> {
+        self.place().wait()
+    }
+
+    /// Get out of line once the task has what it waited for, taking every value handed
+    /// to it
+    fn finish(mut self) -> Vec<Entry<T>> {
+        // Safety: The place is only taken here and when dropped
+        self.place.take().unwrap().leave()
+    }
+}
+
+impl<T> Drop for InLine<'_, T> {
+    fn drop(&mut self) {
+        let left = self.place.take().map(Place::leave).unwrap_or_default();
+        for entry in left {
+            self.inner.push(entry);
+        }
     }
 }
 
@@ -230,10 +1453,7 @@ impl<T: Default> Pool<T> {
     /// use tub::Pool;
     /// let pool: Pool<u32> = Pool::from_default(10);
     /// ```
-    pub fn from_default(capacity: usize) -> Self
-    where
-        T: Default,
-    {
+    pub fn from_default(capacity: usize) -> Self {
         Pool::from_initializer(capacity, T::default)
     }
 }
@@ -246,10 +1466,7 @@ impl<T: Copy> Pool<T> {
     /// use tub::Pool;
     /// let pool = Pool::from_copy(10, 123);
     /// ```
-    pub fn from_copy(capacity: usize, value: T) -> Self
-    where
-        T: Copy,
-    {
+    pub fn from_copy(capacity: usize, value: T) -> Self {
         Pool::from_initializer(capacity, move || value)
     }
 }
@@ -262,11 +1479,40 @@ impl<T: Clone> Pool<T> {
     /// use tub::Pool;
     /// let pool = Pool::from_clone(10, &123);
     /// ```
-    pub fn from_clone(capacity: usize, value: &T) -> Self
-    where
-        T: Clone,
-    {
-        Pool::from_initializer(capacity, move || value.clone())
+    pub fn from_clone(capacity: usize, value: &T) -> Self {
+        Pool::from_initializer(capacity, || value.clone())
+    }
+}
+
+impl<T> Guard<T> {
+    /// Take the value out of the pool for good.
+    ///
+    /// The pool no longer counts the value towards its size, so a pool with an
+    /// initializer or a [`Manager`] can create a replacement the next time it needs one.
+    ///
+    /// This is an associated function rather than a method so it doesn't shadow
+    /// a method of the same name on `T`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tub::{Guard, Pool};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///   let pool: Pool<u32> = Pool::from_default(2);
+    ///   let value = pool.acquire().await.unwrap();
+    ///
+    ///   let value: u32 = Guard::detach(value);
+    ///   assert_eq!(pool.status().size, 1);
+    /// }
+    /// ```
+    pub fn detach(mut guard: Self) -> T {
+        // Safety: The value is always Some
+        let value = guard.value.take().unwrap();
+        guard.inner.end_use(&value, guard.origin.id, guard.acquired);
+        guard.inner.release();
+        value
     }
 }
 
@@ -280,7 +1526,7 @@ impl<T> Drop for Guard<T> {
     /// async fn main() {
     ///   let pool: Pool<u32> = Pool::from_default(1);
     ///   assert_eq!(pool.remaining_capacity(), 1);
-    ///   let mut value = pool.acquire().await;
+    ///   let mut value = pool.acquire().await.unwrap();
     ///
     ///   // Return the value to the pool
     ///   drop(value);
@@ -290,9 +1536,8 @@ impl<T> Drop for Guard<T> {
     #[inline]
     fn drop(&mut self) {
         if let Some(value) = self.value.take() {
-            // Safety: The queue will never be full when a Guard is alive
-            let _ = self.inner.queue.push(value);
-            self.inner.notify.notify_one();
+            self.inner.end_use(&value, self.origin.id, self.acquired);
+            self.inner.put(value, self.origin);
         }
     }
 }
@@ -308,7 +1553,7 @@ impl<T> Deref for Guard<T> {
     /// #[tokio::main]
     /// async fn main() {
     ///   let pool: Pool<u32> = Pool::from_vec(vec![0]);
-    ///   let mut box1 = pool.acquire().await;
+    ///   let mut box1 = pool.acquire().await.unwrap();
     ///
     ///   // Read the value
     ///   assert_eq!(*box1, 0);
@@ -329,7 +1574,7 @@ impl<T> DerefMut for Guard<T> {
     /// #[tokio::main]
     /// async fn main() {
     ///   let pool: Pool<u32> = Pool::from_vec(vec![0]);
-    ///   let mut value = pool.acquire().await;
+    ///   let mut value = pool.acquire().await.unwrap();
     ///   assert_eq!(*value, 0);
     ///
     ///   // Mutate the value
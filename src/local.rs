@@ -0,0 +1,434 @@
+use crate::AcquireError;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+/// A pool for a single thread, for values that can't be sent between threads
+///
+/// [`LocalPool`] works like a [`Pool`](crate::Pool), but is built on [`Rc`] and
+/// [`RefCell`] instead of atomics and locks, so neither the pool nor its values need to be
+/// [`Send`] or [`Sync`]. That makes it a fit for values such as [`Rc`] handles, and for
+/// tasks run by a `tokio::task::LocalSet` or any other single threaded executor.
+///
+/// Tasks waiting for a value are served in the order they started waiting: a value
+/// coming back to the pool is handed straight to the first of them. None of the
+/// [`Builder`](crate::Builder) settings apply to a [`LocalPool`].
+///
+/// # Examples
+///
+/// ```
+/// use std::rc::Rc;
+/// use tub::LocalPool;
+///
+/// #[tokio::main]
+/// async fn main() {
+///   let pool = LocalPool::from_vec(vec![Rc::new(1), Rc::new(2)]);
+///
+///   let value = pool.acquire().await.unwrap();
+///   assert_eq!(pool.remaining_capacity(), 1);
+///
+///   // Return the value to the pool
+///   drop(value);
+///   assert_eq!(pool.remaining_capacity(), 2);
+/// }
+/// ```
+pub struct LocalPool<T> {
+    inner: Rc<LocalInner<T>>,
+}
+
+impl<T> Clone for LocalPool<T> {
+    fn clone(&self) -> Self {
+        LocalPool {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+struct LocalInner<T> {
+    idle: RefCell<VecDeque<T>>,
+    /// Tasks waiting for a value, in the order they started waiting
+    waiters: RefCell<VecDeque<Rc<Waiter<T>>>>,
+    /// The number of values owned by the pool, idle or in use
+    size: Cell<usize>,
+    /// The most values the initializer may bring the pool to
+    max_size: usize,
+    /// Creates replacements for detached values
+    init: Option<Box<dyn Fn() -> T>>,
+}
+
+/// A task waiting for a value, shared between its [`Wait`] future and the pool
+struct Waiter<T> {
+    /// The value handed to the task
+    value: Cell<Option<T>>,
+    waker: Cell<Option<Waker>>,
+}
+
+/// A handle to a value from a [`LocalPool`], returning the value to the pool when dropped
+///
+/// # Examples
+///
+/// ```
+/// use tub::{LocalGuard, LocalPool};
+///
+/// #[tokio::main]
+/// async fn main() {
+///   let pool: LocalPool<u32> = LocalPool::from_default(1);
+///   let mut value: LocalGuard<u32> = pool.acquire().await.unwrap();
+///   *value += 1;
+/// }
+/// ```
+pub struct LocalGuard<T> {
+    /// A value from the pool
+    /// Option is used to play nicely with borrowing rules
+    value: Option<T>,
+    /// A reference to the pool used to return the value when dropped
+    inner: Rc<LocalInner<T>>,
+}
+
+impl<T> LocalPool<T> {
+    fn new(values: VecDeque<T>, max_size: usize, init: Option<Box<dyn Fn() -> T>>) -> Self {
+        LocalPool {
+            inner: Rc::new(LocalInner {
+                size: Cell::new(values.len()),
+                idle: RefCell::new(values),
+                waiters: RefCell::new(VecDeque::new()),
+                max_size,
+                init,
+            }),
+        }
+    }
+
+    /// Acquire a value from the pool, waiting until one is returned if none is idle.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AcquireError::Exhausted`] if the pool has no values left, e.g. because
+    /// every one of them was [detached](LocalGuard::detach), and can't create new ones.
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::LocalPool;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///    let pool = LocalPool::from_vec(vec![7]);
+    ///    assert_eq!(*pool.acquire().await.unwrap(), 7);
+    /// }
+    /// ```
+    pub async fn acquire(&self) -> Result<LocalGuard<T>, AcquireError> {
+        if let Some(guard) = self.try_acquire() {
+            return Ok(guard);
+        }
+        if let Some(value) = self.inner.create() {
+            return Ok(self.guard(value));
+        }
+
+        let wait = Wait {
+            inner: &self.inner,
+            waiter: None,
+        };
+        let value = wait.await?;
+        Ok(self.guard(value))
+    }
+
+    /// Try to acquire an idle value without waiting.
+    ///
+    /// Returns [`None`] if no value is idle.
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::LocalPool;
+    ///
+    /// let pool: LocalPool<u32> = LocalPool::from_default(1);
+    /// let value = pool.try_acquire().unwrap();
+    /// assert!(pool.try_acquire().is_none());
+    /// ```
+    pub fn try_acquire(&self) -> Option<LocalGuard<T>> {
+        let value = self.inner.idle.borrow_mut().pop_front()?;
+        Some(self.guard(value))
+    }
+
+    /// Get the number of available values in the pool
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::LocalPool;
+    /// let pool = LocalPool::from_iter(0..10);
+    /// assert_eq!(pool.remaining_capacity(), 10);
+    /// ```
+    pub fn remaining_capacity(&self) -> usize {
+        self.inner.idle.borrow().len()
+    }
+
+    /// Create a new pool from a vector of values
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::LocalPool;
+    /// let pool = LocalPool::from_vec(vec![1, 2, 3]);
+    /// ```
+    pub fn from_vec(vec: Vec<T>) -> Self {
+        let max_size = vec.len();
+        LocalPool::new(vec.into(), max_size, None)
+    }
+
+    /// Create a new pool from an initializer.
+    ///
+    /// The initializer is called once for each value in the pool, and again
+    /// whenever a value needs to be replaced.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::rc::Rc;
+    /// use tub::LocalPool;
+    /// let pool = LocalPool::from_initializer(10, || Rc::new(0));
+    /// ```
+    pub fn from_initializer<F>(capacity: usize, init: F) -> Self
+    where
+        F: Fn() -> T + 'static,
+    {
+        let values = (0..capacity).map(|_| init()).collect();
+        LocalPool::new(values, capacity, Some(Box::new(init)))
+    }
+
+    /// Create a new pool from an iterator
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::LocalPool;
+    /// let pool = LocalPool::from_iter(0..10);
+    /// ```
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_iter<I>(iterable: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+    {
+        LocalPool::from_vec(iterable.into_iter().collect())
+    }
+
+    fn guard(&self, value: T) -> LocalGuard<T> {
+        LocalGuard {
+            value: Some(value),
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T: Default + 'static> LocalPool<T> {
+    /// Create a new pool with a default value
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::LocalPool;
+    /// let pool: LocalPool<u32> = LocalPool::from_default(10);
+    /// ```
+    pub fn from_default(capacity: usize) -> Self {
+        LocalPool::from_initializer(capacity, T::default)
+    }
+}
+
+impl<T: Copy + 'static> LocalPool<T> {
+    /// Create a new pool with a copy of a value
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::LocalPool;
+    /// let pool = LocalPool::from_copy(10, 123);
+    /// ```
+    pub fn from_copy(capacity: usize, value: T) -> Self {
+        LocalPool::from_initializer(capacity, move || value)
+    }
+}
+
+impl<T: Clone + 'static> LocalPool<T> {
+    /// Create a new pool with a clone of a value
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::LocalPool;
+    /// let pool = LocalPool::from_clone(10, &123);
+    /// ```
+    pub fn from_clone(capacity: usize, value: &T) -> Self {
+        let value = value.clone();
+        LocalPool::from_initializer(capacity, move || value.clone())
+    }
+}
+
+impl<T> LocalInner<T> {
+    /// Create a value with the initializer, if there is room for one
+    fn create(&self) -> Option<T> {
+        let init = self.init.as_ref()?;
+        if self.size.get() >= self.max_size {
+            return None;
+        }
+        let value = init();
+        self.size.set(self.size.get() + 1);
+        Some(value)
+    }
+
+    /// Whether the pool has no values and can't create any, so waiting would never finish
+    fn is_exhausted(&self) -> bool {
+        self.size.get() == 0 && (self.init.is_none() || self.max_size == 0)
+    }
+
+    /// Hand `value` to the first waiting task, or make it idle if no task is waiting
+    fn put(&self, value: T) {
+        let waiter = self.waiters.borrow_mut().pop_front();
+        match waiter {
+            Some(waiter) => {
+                waiter.value.set(Some(value));
+                if let Some(waker) = waiter.waker.take() {
+                    waker.wake();
+                }
+            }
+            None => self.idle.borrow_mut().push_back(value),
+        }
+    }
+
+    /// Stop counting a value that was taken out of the pool for good
+    fn release(&self) {
+        self.size.set(self.size.get() - 1);
+        if self.waiters.borrow().is_empty() {
+            return;
+        }
+
+        if let Some(value) = self.create() {
+            self.put(value);
+        } else if self.is_exhausted() {
+            // Nothing will ever come back, so every waiter has to find out
+            let waiters = self.waiters.borrow();
+            let wakers: Vec<_> = waiters.iter().filter_map(|w| w.waker.take()).collect();
+            drop(waiters);
+            for waker in wakers {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// The future that waits in line for a value from a [`LocalPool`]
+struct Wait<'a, T> {
+    inner: &'a LocalInner<T>,
+    /// Our place in line, once we have joined it
+    waiter: Option<Rc<Waiter<T>>>,
+}
+
+impl<T> Future for Wait<'_, T> {
+    type Output = Result<T, AcquireError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let inner = this.inner;
+        let waiter = this.waiter.get_or_insert_with(|| {
+            let waiter = Rc::new(Waiter {
+                value: Cell::new(None),
+                waker: Cell::new(None),
+            });
+            inner.waiters.borrow_mut().push_back(waiter.clone());
+            waiter
+        });
+
+        // The pool took us out of line when it handed us the value
+        if let Some(value) = waiter.value.take() {
+            this.waiter = None;
+            return Poll::Ready(Ok(value));
+        }
+        if inner.is_exhausted() {
+            return Poll::Ready(Err(AcquireError::Exhausted));
+        }
+
+        waiter.waker.set(Some(cx.waker().clone()));
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for Wait<'_, T> {
+    fn drop(&mut self) {
+        if let Some(waiter) = self.waiter.take() {
+            self.inner
+                .waiters
+                .borrow_mut()
+                .retain(|other| !Rc::ptr_eq(other, &waiter));
+            // Pass on a value we were handed but never took
+            if let Some(value) = waiter.value.take() {
+                self.inner.put(value);
+            }
+        }
+    }
+}
+
+impl<T> LocalGuard<T> {
+    /// Take the value out of the pool for good.
+    ///
+    /// The pool no longer counts the value towards its size, so a pool with an
+    /// initializer can create a replacement the next time it needs one.
+    ///
+    /// This is an associated function rather than a method so it doesn't shadow
+    /// a method of the same name on `T`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tub::{LocalGuard, LocalPool};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///   let pool: LocalPool<u32> = LocalPool::from_vec(vec![1, 2]);
+    ///   let value = pool.acquire().await.unwrap();
+    ///
+    ///   let value: u32 = LocalGuard::detach(value);
+    ///   assert_eq!(pool.remaining_capacity(), 1);
+    /// }
+    /// ```
+    pub fn detach(mut guard: Self) -> T {
+        // Safety: The value is always Some
+        let value = guard.value.take().unwrap();
+        guard.inner.release();
+        value
+    }
+}
+
+impl<T> Drop for LocalGuard<T> {
+    fn drop(&mut self) {
+        if let Some(value) = self.value.take() {
+            self.inner.put(value);
+        }
+    }
+}
+
+impl<T> Deref for LocalGuard<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // Safety: The value is always Some
+        self.value.as_ref().unwrap()
+    }
+}
+
+impl<T> DerefMut for LocalGuard<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // Safety: The value is always Some
+        self.value.as_mut().unwrap()
+    }
+}
+
+impl<T, I> From<I> for LocalPool<T>
+where
+    I: IntoIterator<Item = T>,
+{
+    /// # Examples
+    ///
+    /// ```
+    /// use tub::LocalPool;
+    ///
+    /// let pool: LocalPool<u32> = (0..10).into();
+    /// assert_eq!(pool.remaining_capacity(), 10);
+    /// ```
+    fn from(iter: I) -> Self {
+        LocalPool::from_iter(iter)
+    }
+}
@@ -0,0 +1,127 @@
+use std::error::Error;
+use std::future::Future;
+use std::pin::Pin;
+
+/// An error from a [`Manager`] with its concrete type erased
+pub(crate) type BoxError = Box<dyn Error + Send + Sync>;
+
+pub(crate) type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Creates, recycles and destroys the values in a [`Pool`](crate::Pool)
+///
+/// A pool built with [`Pool::with_manager`](crate::Pool::with_manager) starts empty
+/// and asks its manager for new values on demand, up to its maximum size.
+///
+/// # Examples
+///
+/// ```
+/// use std::convert::Infallible;
+/// use tub::{Manager, Pool};
+///
+/// struct Connections;
+///
+/// impl Manager for Connections {
+///     type Type = String;
+///     type Error = Infallible;
+///
+///     async fn create(&self) -> Result<String, Infallible> {
+///         Ok(String::from("connection"))
+///     }
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let pool = Pool::with_manager(Connections, 10);
+///     assert_eq!(pool.remaining_capacity(), 0);
+///
+///     let connection = pool.acquire().await.unwrap();
+///     assert_eq!(*connection, "connection");
+/// }
+/// ```
+pub trait Manager: Send + Sync + 'static {
+    /// The type of value in the pool
+    type Type: Send;
+    /// The error returned when a value can't be created or recycled
+    type Error: Error + Send + Sync + 'static;
+
+    /// Create a new value
+    fn create(&self) -> impl Future<Output = Result<Self::Type, Self::Error>> + Send;
+
+    /// Check and prepare an idle value before it is handed out again.
+    ///
+    /// If this fails, the value is destroyed with [`Manager::detach`] and the pool
+    /// moves on to another value. The default implementation accepts every value.
+    ///
+    /// [`Pool::try_acquire`](crate::Pool::try_acquire) polls this once and keeps the value
+    /// idle if it isn't done by then, so the future may be dropped part way through.
+    fn recycle(
+        &self,
+        value: &mut Self::Type,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        let _ = value;
+        async { Ok(()) }
+    }
+
+    /// Destroy a value that is leaving the pool for good.
+    ///
+    /// The default implementation drops the value.
+    fn detach(&self, value: Self::Type) {
+        drop(value);
+    }
+}
+
+/// An object-safe version of [`Manager`] so that [`Pool`](crate::Pool) doesn't need
+/// the manager's type as a parameter
+pub(crate) trait DynManager<T>: Send + Sync {
+    fn create(&self) -> BoxFuture<'_, Result<T, BoxError>>;
+    fn recycle<'a>(&'a self, value: &'a mut T) -> BoxFuture<'a, Result<(), BoxError>>;
+    fn detach(&self, value: T);
+}
+
+impl<M: Manager> DynManager<M::Type> for M {
+    fn create(&self) -> BoxFuture<'_, Result<M::Type, BoxError>> {
+        Box::pin(async move { Manager::create(self).await.map_err(BoxError::from) })
+    }
+
+    fn recycle<'a>(&'a self, value: &'a mut M::Type) -> BoxFuture<'a, Result<(), BoxError>> {
+        Box::pin(async move { Manager::recycle(self, value).await.map_err(BoxError::from) })
+    }
+
+    fn detach(&self, value: M::Type) {
+        Manager::detach(self, value)
+    }
+}
+
+/// Where a pool gets new values from
+pub(crate) enum Source<T> {
+    /// A synchronous initializer, from [`Builder::build_with_initializer`](crate::Builder::build_with_initializer)
+    Initializer(Box<dyn Fn() -> T + Send + Sync>),
+    /// An asynchronous [`Manager`]
+    Manager(Box<dyn DynManager<T>>),
+}
+
+impl<T> Source<T> {
+    /// Create a new value
+    pub(crate) async fn create(&self) -> Result<T, BoxError> {
+        match self {
+            Source::Initializer(init) => Ok(init()),
+            Source::Manager(manager) => manager.create().await,
+        }
+    }
+
+    /// Prepare an idle value to be handed out again, returning whether it is still usable
+    pub(crate) async fn recycle(&self, value: &mut T) -> bool {
+        match self {
+            Source::Initializer(_) => true,
+            Source::Manager(manager) => manager.recycle(value).await.is_ok(),
+        }
+    }
+
+    /// Destroy a value that is leaving the pool
+    pub(crate) fn detach(&self, value: T) {
+        match self {
+            Source::Initializer(_) => drop(value),
+            Source::Manager(manager) => manager.detach(value),
+        }
+    }
+}
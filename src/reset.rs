@@ -0,0 +1,62 @@
+use std::collections::{HashMap, VecDeque};
+use std::hash::BuildHasher;
+
+/// Values that can be cleared for the next user of a [`Pool`](crate::Pool)
+///
+/// Build a pool with [`Builder::reset_on_return`](crate::Builder::reset_on_return) to
+/// reset every value when its [`Guard`](crate::Guard) is dropped, so callers don't have
+/// to remember to clear them.
+///
+/// # Examples
+///
+/// ```
+/// use tub::Pool;
+///
+/// #[tokio::main]
+/// async fn main() {
+///   let pool = Pool::builder()
+///       .reset_on_return()
+///       .build(vec![String::new()]);
+///
+///   let mut scratch = pool.acquire().await.unwrap();
+///   scratch.push_str("stale data");
+///   drop(scratch);
+///
+///   assert!(pool.acquire().await.unwrap().is_empty());
+/// }
+/// ```
+pub trait Reset {
+    /// Clear the value, ideally keeping any allocated capacity
+    fn reset(&mut self);
+}
+
+impl<T> Reset for Vec<T> {
+    fn reset(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T> Reset for VecDeque<T> {
+    fn reset(&mut self) {
+        self.clear();
+    }
+}
+
+impl Reset for String {
+    fn reset(&mut self) {
+        self.clear();
+    }
+}
+
+impl<K, V, S: BuildHasher> Reset for HashMap<K, V, S> {
+    fn reset(&mut self) {
+        self.clear();
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl Reset for bytes::BytesMut {
+    fn reset(&mut self) {
+        self.clear();
+    }
+}
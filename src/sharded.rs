@@ -0,0 +1,248 @@
+use crate::builder::Config;
+use crate::manager::Source;
+use crate::{AcquireError, Builder, Guard, Pool, Priority, Waiting};
+use std::future::{poll_fn, Future};
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::atomic::{AtomicBool, AtomicUsize};
+use std::sync::Arc;
+use std::task::Poll;
+use std::time::Duration;
+
+/// Hands each thread a home shard, spreading threads over the shards in turn
+static NEXT_HOME: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static HOME: usize = NEXT_HOME.fetch_add(1, Relaxed);
+}
+
+/// A pool split into shards, so that tasks on different threads rarely touch the same queue
+///
+/// Each shard is a [`Pool`] of its own. A thread acquires values from its home shard,
+/// and only steals from the other shards when its own has no idle value. A value always
+/// goes back to the shard it came from when its [`Guard`] is dropped, so the values stay
+/// spread over the shards.
+///
+/// Sharding pays off when many threads acquire and return values at a high rate. For a
+/// few values, or values held for a long time, a single [`Pool`] is simpler and just as
+/// fast.
+///
+/// # Examples
+///
+/// ```
+/// use tub::ShardedPool;
+///
+/// #[tokio::main]
+/// async fn main() {
+///   let pool = ShardedPool::from_vec(vec![0u32; 64]);
+///
+///   let mut value = pool.acquire().await.unwrap();
+///   *value += 1;
+///   drop(value);
+///   assert_eq!(pool.remaining_capacity(), 64);
+/// }
+/// ```
+pub struct ShardedPool<T> {
+    shards: Arc<[Pool<T>]>,
+}
+
+impl<T> Clone for ShardedPool<T> {
+    fn clone(&self) -> Self {
+        ShardedPool {
+            shards: self.shards.clone(),
+        }
+    }
+}
+
+impl<T> ShardedPool<T> {
+    /// Create a pool from a vector of values, with a shard for every thread the machine
+    /// can run in parallel
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::ShardedPool;
+    /// let pool = ShardedPool::from_vec(vec![1, 2, 3]);
+    /// assert_eq!(pool.remaining_capacity(), 3);
+    /// ```
+    pub fn from_vec(values: Vec<T>) -> Self {
+        Builder::new().build_sharded(values, default_shards())
+    }
+
+    /// Split the values over `shards` pools sharing `config`, which together may own up
+    /// to `max_size` values, or any number if there is no maximum.
+    pub(crate) fn from_parts(
+        shards: usize,
+        values: Vec<T>,
+        max_size: Option<usize>,
+        config: Config<T>,
+        mut source: impl FnMut() -> Option<Source<T>>,
+    ) -> Self {
+        let shards = shards.max(1);
+        let mut split: Vec<Vec<T>> = (0..shards).map(|_| Vec::new()).collect();
+        for (index, value) in values.into_iter().enumerate() {
+            split[index % shards].push(value);
+        }
+
+        let config = Arc::new(config);
+        let shards = split
+            .into_iter()
+            .enumerate()
+            .map(|(index, values)| {
+                // The first shards take the remainder, just like they take the
+                // remaining values above
+                let max_size = match max_size {
+                    Some(max_size) => max_size / shards + usize::from(index < max_size % shards),
+                    None => usize::MAX,
+                };
+                let max_size = max_size.max(values.len());
+                Pool::from_parts(max_size, values, source(), config.clone(), None)
+            })
+            .collect();
+        ShardedPool { shards }
+    }
+
+    /// Acquire a value from the pool, like [`Pool::acquire`].
+    ///
+    /// The value comes from the calling thread's home shard if it has one idle, or else
+    /// from the first other shard that does. If none does, the task waits for whichever
+    /// shard has a value first.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`Pool::acquire`].
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::ShardedPool;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///    let pool = ShardedPool::from_vec(vec![7]);
+    ///    assert_eq!(*pool.acquire().await.unwrap(), 7);
+    /// }
+    /// ```
+    pub async fn acquire(&self) -> Result<Guard<T>, AcquireError> {
+        for shard in self.by_distance() {
+            if let Some(guard) = shard.take_idle().await {
+                return Ok(guard);
+            }
+        }
+
+        #[cfg(feature = "time")]
+        if let Some(timeout) = self.shards[0].inner.config.wait_timeout {
+            return match tokio::time::timeout(timeout, self.wait()).await {
+                Ok(result) => result,
+                Err(_) => Err(self.shards[self.home()].inner.timed_out()),
+            };
+        }
+
+        self.wait().await
+    }
+
+    /// Try to acquire an idle value without waiting, like [`Pool::try_acquire`].
+    ///
+    /// Returns [`None`] if no shard has an idle value.
+    ///
+    /// # Examples
+    /// ```
+    /// use tub::ShardedPool;
+    ///
+    /// let pool = ShardedPool::from_vec(vec![1]);
+    /// let value = pool.try_acquire().unwrap();
+    /// assert!(pool.try_acquire().is_none());
+    /// ```
+    pub fn try_acquire(&self) -> Option<Guard<T>> {
+        self.by_distance().find_map(Pool::try_acquire)
+    }
+
+    /// Get the number of idle values in every shard together
+    pub fn remaining_capacity(&self) -> usize {
+        self.shards.iter().map(Pool::remaining_capacity).sum()
+    }
+
+    /// Get the shards, e.g. to check their [`Pool::status`]
+    ///
+    /// A task waiting for a value waits at every shard, but only counts as a waiter of
+    /// the home shard of its thread.
+    pub fn shards(&self) -> &[Pool<T>] {
+        &self.shards
+    }
+
+    /// Run [`Pool::maintain`] for every shard
+    pub async fn maintain(&self) {
+        for shard in self.shards.iter() {
+            shard.maintain().await;
+        }
+    }
+
+    /// Close every shard, see [`Pool::close`]
+    pub fn close(&self) {
+        for shard in self.shards.iter() {
+            shard.close();
+        }
+    }
+
+    /// The index of the calling thread's home shard
+    fn home(&self) -> usize {
+        HOME.with(|home| *home) % self.shards.len()
+    }
+
+    /// Every shard, starting with the calling thread's home shard
+    fn by_distance(&self) -> impl Iterator<Item = &Pool<T>> {
+        let home = self.home();
+        self.shards[home..].iter().chain(&self.shards[..home])
+    }
+
+    /// Wait in line at every shard until one of them has a value
+    async fn wait(&self) -> Result<Guard<T>, AcquireError> {
+        // Only one shard at a time may create a value, starting with the home shard
+        // since it is polled first
+        let claim = AtomicBool::new(false);
+        let mut waits: Vec<_> = self
+            .by_distance()
+            .map(|shard| Box::pin(shard.wait_many(1, Priority::Normal, Some(&claim))))
+            .collect();
+
+        let _waiting = Waiting::new(&self.shards[self.home()].inner);
+        // Dropping the other waits gives back anything they were handed
+        poll_fn(|cx| {
+            let mut index = 0;
+            while index < waits.len() {
+                match waits[index].as_mut().poll(cx) {
+                    Poll::Ready(Ok(set)) => return Poll::Ready(Ok(set.into_guard())),
+                    // This shard has no values and can't create any, unlike the others
+                    Poll::Ready(Err(AcquireError::Exhausted)) if waits.len() > 1 => {
+                        drop(waits.remove(index));
+                    }
+                    Poll::Ready(Err(error)) => return Poll::Ready(Err(error)),
+                    Poll::Pending => index += 1,
+                }
+            }
+            Poll::Pending
+        })
+        .await
+    }
+}
+
+impl<T> Pool<T> {
+    /// Take an idle value without waiting in line, awaiting the checks that
+    /// [`Pool::try_acquire`] only polls once
+    async fn take_idle(&self) -> Option<Guard<T>> {
+        let inner = &self.inner;
+        if inner.is_closed() || !inner.waiters.may_skip_line() {
+            return None;
+        }
+
+        while let Some(entry) = inner.pop() {
+            let origin = entry.origin;
+            if let Some(value) = inner.prepare(entry).await {
+                return Some(self.guard(value, origin, Duration::ZERO));
+            }
+        }
+        None
+    }
+}
+
+/// One shard for every thread the machine can run in parallel
+fn default_shards() -> usize {
+    std::thread::available_parallelism().map_or(1, usize::from)
+}
@@ -0,0 +1,89 @@
+use crossbeam_epoch::{self as epoch, Atomic, Owned};
+use std::mem::ManuallyDrop;
+use std::ptr;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+
+/// A lock-free stack, handing out the most recently pushed value first
+///
+/// Popped nodes are freed through `crossbeam_epoch`, once no other thread can be
+/// reading them.
+pub(crate) struct Stack<T> {
+    head: Atomic<Node<T>>,
+    /// May count a value that is still being pushed, but never one that was popped
+    len: AtomicUsize,
+}
+
+struct Node<T> {
+    /// Moved out when the node is popped, so that only the node itself is freed later
+    value: ManuallyDrop<T>,
+    next: Atomic<Node<T>>,
+}
+
+// Safety: Values are moved in and out of the stack, never shared between threads
+unsafe impl<T: Send> Send for Stack<T> {}
+unsafe impl<T: Send> Sync for Stack<T> {}
+
+impl<T> Stack<T> {
+    pub(crate) fn new() -> Self {
+        Stack {
+            head: Atomic::null(),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn push(&self, value: T) {
+        // Counted first, so that a pop racing with this push can't take the count below 0
+        self.len.fetch_add(1, Relaxed);
+
+        let mut node = Owned::new(Node {
+            value: ManuallyDrop::new(value),
+            next: Atomic::null(),
+        });
+        let guard = epoch::pin();
+        loop {
+            let head = self.head.load(Relaxed, &guard);
+            node.next.store(head, Relaxed);
+            match self
+                .head
+                .compare_exchange(head, node, Release, Relaxed, &guard)
+            {
+                Ok(_) => return,
+                Err(error) => node = error.new,
+            }
+        }
+    }
+
+    pub(crate) fn pop(&self) -> Option<T> {
+        let guard = epoch::pin();
+        loop {
+            let head = self.head.load(Acquire, &guard);
+            // Safety: The node can't be freed while the guard is pinned
+            let node = unsafe { head.as_ref() }?;
+            let next = node.next.load(Relaxed, &guard);
+            if self
+                .head
+                .compare_exchange(head, next, Relaxed, Relaxed, &guard)
+                .is_ok()
+            {
+                self.len.fetch_sub(1, Relaxed);
+                // Safety: Only the thread that unlinked the node reads its value, and
+                // the node is freed after every thread that could see it has moved on
+                unsafe {
+                    guard.defer_destroy(head);
+                    return Some(ManuallyDrop::into_inner(ptr::read(&node.value)));
+                }
+            }
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len.load(Relaxed)
+    }
+}
+
+impl<T> Drop for Stack<T> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+    }
+}
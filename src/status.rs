@@ -0,0 +1,66 @@
+use std::sync::atomic::AtomicUsize;
+
+/// A snapshot of a [`Pool`](crate::Pool)'s state, from [`Pool::status`](crate::Pool::status)
+///
+/// The fields are read one at a time while the pool is in use, so they may be
+/// slightly out of step with each other. The cumulative counters start at zero
+/// when the pool is built.
+///
+/// # Examples
+///
+/// ```
+/// use tub::Pool;
+///
+/// #[tokio::main]
+/// async fn main() {
+///   let pool = Pool::from_copy(10, 0);
+///   let _value = pool.acquire().await.unwrap();
+///
+///   let status = pool.status();
+///   assert_eq!(status.size, 10);
+///   assert_eq!(status.idle, 9);
+///   assert_eq!(status.in_use, 1);
+///   assert_eq!(status.acquires, 1);
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PoolStatus {
+    /// The number of values the pool owns, whether idle, in use, or being created
+    pub size: usize,
+    /// The number of values waiting in the pool
+    pub idle: usize,
+    /// The number of values held by a [`Guard`](crate::Guard)
+    pub in_use: usize,
+    /// The number of tasks waiting in [`Pool::acquire`](crate::Pool::acquire) for a value
+    pub waiters: usize,
+    /// The number of [`Guard`](crate::Guard)s handed out
+    pub acquires: usize,
+    /// The number of [`Guard`](crate::Guard)s dropped or detached
+    pub releases: usize,
+    /// The number of acquires that gave up with [`AcquireError::Timeout`](crate::AcquireError::Timeout)
+    pub timeouts: usize,
+    /// The number of values created by the pool's initializer or [`Manager`](crate::Manager)
+    /// after it was built
+    pub created: usize,
+    /// The number of values destroyed by the pool, e.g. because they expired,
+    /// failed a check, or the pool was closed
+    pub destroyed: usize,
+    /// The number of values that failed a check and were destroyed
+    pub failed_checks: usize,
+}
+
+/// The cumulative counters behind [`PoolStatus`]
+///
+/// Only what can't be worked out from the others is counted, since the acquire and
+/// release counters are updated on every use of the pool.
+#[derive(Default)]
+pub(crate) struct Counters {
+    pub(crate) waiters: AtomicUsize,
+    pub(crate) acquires: AtomicUsize,
+    pub(crate) releases: AtomicUsize,
+    pub(crate) timeouts: AtomicUsize,
+    pub(crate) created: AtomicUsize,
+    pub(crate) destroyed: AtomicUsize,
+    pub(crate) failed_checks: AtomicUsize,
+}
@@ -0,0 +1,74 @@
+use crate::stack::Stack;
+use crossbeam_queue::{ArrayQueue, SegQueue};
+
+/// The order in which a [`Pool`](crate::Pool) hands out its idle values,
+/// set with [`Builder::queue_mode`](crate::Builder::queue_mode)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum QueueMode {
+    /// Hand out the value that has been idle the longest, spreading use over every value
+    #[default]
+    Fifo,
+    /// Hand out the most recently returned value, so a few values stay in use and the
+    /// rest sit idle until [`Pool::maintain`](crate::Pool::maintain) reaps them
+    Lifo,
+}
+
+/// Lock-free storage for a pool's idle values
+pub(crate) enum Store<T> {
+    /// A fixed-size queue for FIFO pools whose maximum size is small enough to allocate
+    Bounded(ArrayQueue<T>),
+    /// A growable queue for FIFO pools that may grow well past their initial size
+    Unbounded(SegQueue<T>),
+    /// A stack for LIFO pools, which never holds more values than the pool owns
+    Stack(Stack<T>),
+}
+
+/// The most slots allocated up front for values a pool doesn't start with
+const PREALLOCATED: usize = 64;
+
+impl<T> Store<T> {
+    /// Create storage for a pool that starts with `size` values and may own up to
+    /// `max_size`, handed out in the given order
+    pub(crate) fn new(size: usize, max_size: usize, mode: QueueMode) -> Self {
+        match mode {
+            QueueMode::Lifo => Store::Stack(Stack::new()),
+            // Slots for values the pool may never create aren't allocated up front
+            QueueMode::Fifo if max_size > size.max(PREALLOCATED) => {
+                Store::Unbounded(SegQueue::new())
+            }
+            // An ArrayQueue can't be empty, so an empty pool still gets one slot
+            QueueMode::Fifo => Store::Bounded(ArrayQueue::new(max_size.max(1))),
+        }
+    }
+
+    /// Whether the most recently pushed value is popped first
+    pub(crate) fn is_lifo(&self) -> bool {
+        matches!(self, Store::Stack(_))
+    }
+
+    /// Add a value. The caller guarantees that the pool owns at most `max_size` values.
+    pub(crate) fn push(&self, value: T) {
+        match self {
+            // Safety: The queue can hold every value the pool owns
+            Store::Bounded(queue) => drop(queue.push(value)),
+            Store::Unbounded(queue) => queue.push(value),
+            Store::Stack(stack) => stack.push(value),
+        }
+    }
+
+    pub(crate) fn pop(&self) -> Option<T> {
+        match self {
+            Store::Bounded(queue) => queue.pop(),
+            Store::Unbounded(queue) => queue.pop(),
+            Store::Stack(stack) => stack.pop(),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Store::Bounded(queue) => queue.len(),
+            Store::Unbounded(queue) => queue.len(),
+            Store::Stack(stack) => stack.len(),
+        }
+    }
+}
@@ -0,0 +1,46 @@
+//! `tracing` support, which compiles to nothing without the `tracing` feature
+
+/// Emit a `tracing` event, like `tracing::debug!`, if the `tracing` feature is enabled
+macro_rules! event {
+    ($level:ident, $($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        ::tracing::$level!($($arg)+);
+    };
+}
+
+pub(crate) use event;
+
+/// The span covering the time an acquire spends waiting for a value.
+///
+/// The span is only opened once the acquire first has to wait.
+#[derive(Default)]
+pub(crate) struct WaitSpan {
+    #[cfg(feature = "tracing")]
+    span: Option<tracing::Span>,
+}
+
+impl WaitSpan {
+    /// Run `future`, which waits for a value, inside the span
+    #[cfg(feature = "tracing")]
+    pub(crate) fn instrument<F>(
+        &mut self,
+        pool: &str,
+        future: F,
+    ) -> tracing::instrument::Instrumented<F> {
+        use tracing::Instrument;
+
+        let span = self.span.get_or_insert_with(|| {
+            let span = tracing::debug_span!("wait", pool);
+            span.in_scope(|| tracing::debug!(pool, "waiting for a pooled value"));
+            span
+        });
+        future.instrument(span.clone())
+    }
+
+    /// Run `future`, which waits for a value, inside the span
+    #[cfg(not(feature = "tracing"))]
+    #[inline(always)]
+    pub(crate) fn instrument<F>(&mut self, _pool: &str, future: F) -> F {
+        future
+    }
+}
@@ -0,0 +1,350 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::mem;
+use std::pin::{pin, Pin};
+use std::ptr;
+use std::sync::atomic::Ordering::{Relaxed, SeqCst};
+use std::sync::atomic::{fence, AtomicUsize};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+use std::time::{Duration, Instant};
+
+/// How urgently a task needs a value, see [`Pool::acquire_with_priority`](crate::Pool::acquire_with_priority)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Bulk work that can wait for everything else
+    Low,
+    /// The priority of [`Pool::acquire`](crate::Pool::acquire)
+    #[default]
+    Normal,
+    /// Work that must get ahead of normal traffic, such as health checks
+    High,
+}
+
+/// The tasks waiting for a value from a pool, in the order they started waiting
+///
+/// A value coming back to the pool can be [handed](WaitList::hand_off) straight to a
+/// waiter, so that no other task can take it first.
+///
+/// Waiters are served by [`Priority`], and in the order they started waiting within a
+/// priority. A waiter moves up one priority for every `aging` it has waited, so that low
+/// priority waiters are served eventually however many others come along.
+pub(crate) struct WaitList<V> {
+    /// Whether only the waiter at the front of the line may take a value from the pool
+    fair: bool,
+    aging: Duration,
+    /// The number of waiters, so that returning a value doesn't take the lock while
+    /// nobody waits
+    len: AtomicUsize,
+    state: Mutex<State<V>>,
+}
+
+struct State<V> {
+    /// The id of the next waiter to join
+    next_id: u64,
+    /// Sorted by id, since waiters join at the back
+    waiters: VecDeque<Waiter<V>>,
+    /// How many waiters don't have [`Priority::Normal`]. While there are none, the
+    /// front of the line is simply the oldest waiter.
+    prioritized: usize,
+}
+
+impl<V> State<V> {
+    /// Find the index of the waiter with the given id
+    fn position(&self, id: u64) -> usize {
+        // Safety: Waiters are only removed when they leave
+        self.waiters.binary_search_by_key(&id, |w| w.id).unwrap()
+    }
+}
+
+struct Waiter<V> {
+    id: u64,
+    priority: Priority,
+    joined: Instant,
+    waker: Option<Waker>,
+    /// Whether the waiter has been woken and hasn't noticed yet
+    woken: bool,
+    /// How many values the waiter is waiting for
+    wants: usize,
+    /// The values handed to the waiter
+    values: Vec<V>,
+}
+
+impl<V> Waiter<V> {
+    /// Whether the waiter has been handed every value it is waiting for
+    fn is_served(&self) -> bool {
+        self.values.len() >= self.wants
+    }
+
+    /// Mark the waiter as woken, returning the waker to call once the lock is released
+    fn wake(&mut self) -> Option<Waker> {
+        self.woken = true;
+        self.waker.take()
+    }
+}
+
+impl<V> WaitList<V> {
+    pub(crate) fn new(fair: bool, aging: Duration) -> Self {
+        WaitList {
+            fair,
+            aging,
+            len: AtomicUsize::new(0),
+            state: Mutex::new(State {
+                next_id: 0,
+                waiters: VecDeque::new(),
+                prioritized: 0,
+            }),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State<V>> {
+        // The lock is never held while running user code, so poisoning can be ignored
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Get in line at the back at time `joined`, waiting for `wants` values.
+    ///
+    /// Anything that could let the caller stop waiting should be checked again after
+    /// joining, since wakeups from before joining are missed.
+    pub(crate) fn join(&self, priority: Priority, wants: usize, joined: Instant) -> Place<'_, V> {
+        let mut state = self.lock();
+        self.len.fetch_add(1, Relaxed);
+        let id = state.next_id;
+        state.next_id += 1;
+        if priority != Priority::Normal {
+            state.prioritized += 1;
+        }
+        state.waiters.push_back(Waiter {
+            id,
+            priority,
+            joined,
+            waker: None,
+            woken: false,
+            wants,
+            values: Vec::new(),
+        });
+        drop(state);
+
+        // Pairs with the fence in `is_empty`: either the caller sees a value that came
+        // back while it joined, or whoever returned it sees the caller in line
+        fence(SeqCst);
+        Place { list: self, id }
+    }
+
+    /// Whether nobody is waiting, without taking the lock.
+    ///
+    /// Call this after making a value available, so that a task joining at the same
+    /// time either finds the value or is seen here.
+    fn is_empty(&self) -> bool {
+        fence(SeqCst);
+        self.len.load(Relaxed) == 0
+    }
+
+    /// Find the index of the first waiter in line among those matching `filter`
+    fn next(&self, state: &State<V>, filter: impl Fn(&Waiter<V>) -> bool) -> Option<usize> {
+        if state.prioritized == 0 {
+            return state.waiters.iter().position(filter);
+        }
+
+        // Older waiters come first among equals, so only a higher rank takes over
+        let now = Instant::now();
+        let mut first: Option<(usize, u64)> = None;
+        for (index, waiter) in state.waiters.iter().enumerate() {
+            if !filter(waiter) {
+                continue;
+            }
+            let rank = self.rank(waiter, now);
+            if first.map_or(true, |(_, first)| rank > first) {
+                first = Some((index, rank));
+            }
+        }
+        first.map(|(index, _)| index)
+    }
+
+    /// The waiter's priority, raised by how long it has waited
+    fn rank(&self, waiter: &Waiter<V>, now: Instant) -> u64 {
+        let waited = now.saturating_duration_since(waiter.joined).as_nanos();
+        // With no aging at all, every waiter is served in arrival order
+        let raised = waited
+            .checked_div(self.aging.as_nanos())
+            .unwrap_or(u128::MAX);
+        let raised = u64::try_from(raised).unwrap_or(u64::MAX);
+        raised.saturating_add(waiter.priority as u64)
+    }
+
+    /// Whether a task that isn't in line may take a value without waiting its turn
+    pub(crate) fn may_skip_line(&self) -> bool {
+        !self.fair || self.len.load(Relaxed) == 0
+    }
+
+    /// Give `value` to the first waiter in line that still needs one, or give it back
+    /// if there is no such waiter.
+    ///
+    /// The waiter is woken once it has every value it is waiting for. Until then it holds
+    /// on to what it was given, and no waiter behind it is handed anything.
+    pub(crate) fn hand_off(&self, value: V) -> Result<(), V> {
+        if self.is_empty() {
+            return Err(value);
+        }
+
+        let mut state = self.lock();
+        let waiter = match self.next(&state, |w| !w.is_served()) {
+            Some(index) => &mut state.waiters[index],
+            None => return Err(value),
+        };
+
+        waiter.values.push(value);
+        let waker = match waiter.is_served() {
+            true => waiter.wake(),
+            false => None,
+        };
+        drop(state);
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    /// Wake one waiter: the first in line if the list is fair, or otherwise the first
+    /// in line among those that haven't been woken already.
+    pub(crate) fn wake_one(&self) {
+        if self.is_empty() {
+            return;
+        }
+
+        let mut state = self.lock();
+        let index = if self.fair {
+            self.next(&state, |_| true)
+                .filter(|&index| !state.waiters[index].woken)
+        } else {
+            self.next(&state, |waiter| !waiter.woken)
+        };
+
+        let waker = index.and_then(|index| state.waiters[index].wake());
+        drop(state);
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// Wake every waiter
+    pub(crate) fn wake_all(&self) {
+        if self.is_empty() {
+            return;
+        }
+
+        let mut state = self.lock();
+        let wakers: Vec<_> = state.waiters.iter_mut().filter_map(Waiter::wake).collect();
+        drop(state);
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+/// A task's place in a [`WaitList`], which it leaves when this is dropped
+///
+/// Values handed to the waiter that it hasn't taken are dropped along with the place,
+/// so use [`Place::leave`] to get them back instead.
+pub(crate) struct Place<'a, V> {
+    list: &'a WaitList<V>,
+    id: u64,
+}
+
+impl<'a, V> Place<'a, V> {
+    /// Whether this waiter may take a value from the pool: it is first in line,
+    /// or the list isn't fair
+    pub(crate) fn may_take(&self) -> bool {
+        if !self.list.fair {
+            return true;
+        }
+
+        let state = self.list.lock();
+        let first = self.list.next(&state, |_| true);
+        first.map(|index| state.waiters[index].id) == Some(self.id)
+    }
+
+    /// Take a value handed to this waiter, if any
+    pub(crate) fn take(&self) -> Option<V> {
+        let mut state = self.list.lock();
+        let index = state.position(self.id);
+        state.waiters[index].values.pop()
+    }
+
+    /// Wait until this waiter is woken, returning the number of values handed to it
+    pub(crate) fn wait(&self) -> Wait<'_, 'a, V> {
+        Wait { place: self }
+    }
+
+    /// Get out of line, returning the values handed to this waiter that it hasn't taken
+    pub(crate) fn leave(self) -> Vec<V> {
+        let values = self.remove();
+        mem::forget(self);
+        values
+    }
+
+    fn remove(&self) -> Vec<V> {
+        let mut state = self.list.lock();
+        let index = state.position(self.id);
+        let first = self.list.fair && self.list.next(&state, |_| true) == Some(index);
+        // Safety: The index was just found
+        let waiter = state.waiters.remove(index).unwrap();
+        self.list.len.fetch_sub(1, Relaxed);
+        if waiter.priority != Priority::Normal {
+            state.prioritized -= 1;
+        }
+        drop(state);
+
+        // Pass on a wakeup this waiter didn't use. In a fair list the next in line
+        // may also have been held back by this waiter, so it gets a look at the pool.
+        if waiter.woken || first {
+            self.list.wake_one();
+        }
+        waiter.values
+    }
+}
+
+impl<V> Drop for Place<'_, V> {
+    fn drop(&mut self) {
+        drop(self.remove());
+    }
+}
+
+/// The future returned by [`Place::wait`]
+pub(crate) struct Wait<'p, 'a, V> {
+    place: &'p Place<'a, V>,
+}
+
+impl<V> Future for Wait<'_, '_, V> {
+    type Output = usize;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<usize> {
+        let mut state = self.place.list.lock();
+        let index = state.position(self.place.id);
+        let waiter = &mut state.waiters[index];
+
+        if waiter.woken {
+            waiter.woken = false;
+            return Poll::Ready(waiter.values.len());
+        }
+
+        match &mut waiter.waker {
+            Some(waker) if waker.will_wake(cx.waker()) => {}
+            waker => *waker = Some(cx.waker().clone()),
+        }
+        Poll::Pending
+    }
+}
+
+/// Poll `future` once, for callers that can't wait for it to be woken
+pub(crate) fn poll_once<F: Future>(future: F) -> Poll<F::Output> {
+    fn clone(_: *const ()) -> RawWaker {
+        RawWaker::new(ptr::null(), &NOOP)
+    }
+    fn noop(_: *const ()) {}
+    static NOOP: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+
+    // Safety: The waker does nothing, so it can't misuse its null data pointer
+    let waker = unsafe { Waker::from_raw(RawWaker::new(ptr::null(), &NOOP)) };
+    pin!(future).poll(&mut Context::from_waker(&waker))
+}
@@ -1,12 +1,24 @@
 extern crate tub;
 
+use futures::task::LocalSpawnExt;
 use proptest::prelude::*;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::{Cell, RefCell};
+use std::fmt;
 use std::hint::black_box;
+use std::rc::Rc;
+#[cfg(feature = "metrics")]
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::SeqCst;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::runtime::Runtime;
 use tokio::sync::Barrier;
-use tub::Pool;
+use tub::{
+    AcquireError, Guard, KeyedPool, LocalGuard, LocalPool, Manager, Metadata, Percentiles, Pool,
+    Priority, QueueMode, ShardedPool,
+};
 
 #[tokio::test]
 async fn readme() {
@@ -14,7 +26,7 @@ async fn readme() {
     let pool: Pool<Box> = (0..10).map(|_| Box { _value: 123 }).into();
 
     // Get a value from the pool
-    let mut box1 = pool.acquire().await;
+    let mut box1 = pool.acquire().await.unwrap();
 
     // Use the value
     box1.foo();
@@ -64,61 +76,2236 @@ fn test_new_from_default() {
     assert_eq!(pool.remaining_capacity(), 10);
 }
 
+#[test]
+fn test_new_from_values_that_are_not_send() {
+    let value = Rc::new(1);
+    let pool = Pool::from_clone(2, &value);
+    assert_eq!(pool.remaining_capacity(), 2);
+    assert_eq!(Rc::strong_count(&value), 3);
+
+    let pool = Pool::from_initializer(2, || value.clone());
+    assert_eq!(pool.remaining_capacity(), 2);
+}
+
 #[test]
 fn test_new_from_iter() {
     let pool = Pool::from_iter(0..10);
     assert_eq!(pool.remaining_capacity(), 10);
 }
 
-#[test]
-fn test_from_trait_1() {
-    let pool: Pool<u32> = (0..100).into();
-    assert_eq!(pool.remaining_capacity(), 100);
+#[test]
+fn test_from_trait_1() {
+    let pool: Pool<u32> = (0..100).into();
+    assert_eq!(pool.remaining_capacity(), 100);
+}
+
+#[test]
+fn test_from_trait_2() {
+    let pool: Pool<String> = (0..100).map(|_| String::from("hello")).into();
+    assert_eq!(pool.remaining_capacity(), 100);
+}
+
+#[test]
+fn test_from_trait_3() {
+    let pool: Pool<Vec<u32>> = (0..10).map(|_| vec![]).into();
+    assert_eq!(pool.remaining_capacity(), 10);
+}
+
+#[test]
+fn test_clone_a_pool() {
+    let pool = Pool::from_copy(10, 1);
+    let pool2 = pool.clone();
+    assert_eq!(pool.remaining_capacity(), 10);
+    assert_eq!(pool2.remaining_capacity(), 10);
+}
+
+#[tokio::test]
+async fn guarded_value_is_mutable() {
+    let pool = Pool::from_copy(10, 1);
+    let mut box1 = pool.acquire().await.unwrap();
+    assert_eq!(pool.remaining_capacity(), 9);
+    assert_eq!(*box1, 1);
+    *box1 = 2;
+    assert_eq!(*box1, 2);
+}
+
+#[tokio::test]
+async fn mutated_value_is_returned_to_pool() {
+    let pool = Pool::from_copy(1, 1);
+    let mut b = pool.acquire().await.unwrap();
+    assert_eq!(pool.remaining_capacity(), 0);
+    assert_eq!(*b, 1);
+    *b = 2;
+    assert_eq!(*b, 2);
+    drop(b);
+    assert_eq!(pool.remaining_capacity(), 1);
+    let b = pool.acquire().await.unwrap();
+    assert_eq!(pool.remaining_capacity(), 0);
+    assert_eq!(*b, 2);
+}
+
+#[test]
+fn try_acquire_empty_pool() {
+    let pool = Pool::from_copy(2, 1);
+    let a = pool.try_acquire().unwrap();
+    let b = pool.try_acquire().unwrap();
+    assert!(pool.try_acquire().is_none());
+    assert_eq!(pool.remaining_capacity(), 0);
+    drop(a);
+    drop(b);
+    assert_eq!(pool.remaining_capacity(), 2);
+}
+
+#[tokio::test]
+async fn try_acquire_does_not_register_a_waiter() {
+    let pool = Pool::from_copy(1, 1);
+    let guard = pool.acquire().await.unwrap();
+
+    // Failed attempts must not consume the wake-up meant for a real waiter
+    for _ in 0..10 {
+        assert!(pool.try_acquire().is_none());
+    }
+
+    let waiter = tokio::spawn({
+        let pool = pool.clone();
+        async move {
+            let _value = pool.acquire().await.unwrap();
+        }
+    });
+
+    tokio::task::yield_now().await;
+    drop(guard);
+    tokio::time::timeout(Duration::from_secs(1), waiter)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(pool.remaining_capacity(), 1);
+}
+
+#[tokio::test]
+async fn acquire_from_empty_pool_is_exhausted() {
+    let pool: Pool<u32> = Pool::from_vec(vec![]);
+    assert!(matches!(pool.acquire().await, Err(AcquireError::Exhausted)));
+}
+
+#[cfg(feature = "time")]
+#[tokio::test]
+async fn acquire_timeout_expires() {
+    let pool = Pool::from_copy(1, 1);
+    let _guard = pool.acquire().await.unwrap();
+    let result = pool.acquire_timeout(Duration::from_millis(10)).await;
+    assert!(matches!(result, Err(AcquireError::Timeout)));
+}
+
+#[cfg(feature = "time")]
+#[tokio::test]
+async fn acquire_until_expires() {
+    let pool = Pool::from_copy(1, 1);
+    let _guard = pool.acquire().await.unwrap();
+    let deadline = std::time::Instant::now() + Duration::from_millis(10);
+    let result = pool.acquire_until(deadline).await;
+    assert!(matches!(result, Err(AcquireError::Timeout)));
+}
+
+#[cfg(feature = "time")]
+#[tokio::test]
+async fn acquire_timeout_succeeds_when_value_is_returned() {
+    let pool = Pool::from_copy(1, 1);
+    let guard = pool.acquire().await.unwrap();
+    let waiter = tokio::spawn({
+        let pool = pool.clone();
+        async move {
+            pool.acquire_timeout(Duration::from_secs(5))
+                .await
+                .map(|_| ())
+        }
+    });
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    drop(guard);
+    assert!(waiter.await.unwrap().is_ok());
+}
+
+#[cfg(feature = "time")]
+#[tokio::test]
+async fn timed_out_waiter_does_not_lose_wakeup() {
+    let pool = Pool::from_copy(1, 1);
+    let guard = pool.acquire().await.unwrap();
+    let result = pool.acquire_timeout(Duration::from_millis(10)).await;
+    assert!(matches!(result, Err(AcquireError::Timeout)));
+
+    let waiter = tokio::spawn({
+        let pool = pool.clone();
+        async move { pool.acquire().await.map(|_| ()) }
+    });
+    tokio::task::yield_now().await;
+    drop(guard);
+    tokio::time::timeout(Duration::from_secs(1), waiter)
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+}
+
+#[cfg(feature = "time")]
+#[tokio::test]
+async fn builder_wait_timeout_applies_to_acquire() {
+    let pool: Pool<u32> = Pool::builder()
+        .wait_timeout(Duration::from_millis(10))
+        .build(vec![1]);
+    let _guard = pool.acquire().await.unwrap();
+    assert!(matches!(pool.acquire().await, Err(AcquireError::Timeout)));
+}
+
+#[derive(Debug)]
+struct TestError;
+
+impl fmt::Display for TestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("test error")
+    }
+}
+
+impl std::error::Error for TestError {}
+
+/// A manager that counts calls and fails on request
+#[derive(Clone, Default)]
+struct Counter {
+    created: Arc<AtomicUsize>,
+    recycled: Arc<AtomicUsize>,
+    detached: Arc<AtomicUsize>,
+    /// The number of upcoming `create` calls that fail
+    failing_creates: Arc<AtomicUsize>,
+    /// Values below this number fail to recycle
+    broken_below: Arc<AtomicUsize>,
+}
+
+impl Manager for Counter {
+    type Type = usize;
+    type Error = TestError;
+
+    async fn create(&self) -> Result<usize, TestError> {
+        let failing = self
+            .failing_creates
+            .fetch_update(SeqCst, SeqCst, |n| n.checked_sub(1));
+        if failing.is_ok() {
+            return Err(TestError);
+        }
+        Ok(self.created.fetch_add(1, SeqCst))
+    }
+
+    async fn recycle(&self, value: &mut usize) -> Result<(), TestError> {
+        self.recycled.fetch_add(1, SeqCst);
+        if *value < self.broken_below.load(SeqCst) {
+            return Err(TestError);
+        }
+        Ok(())
+    }
+
+    fn detach(&self, _value: usize) {
+        self.detached.fetch_add(1, SeqCst);
+    }
+}
+
+#[tokio::test]
+async fn manager_creates_values_lazily() {
+    let manager = Counter::default();
+    let pool = Pool::with_manager(manager.clone(), 2);
+    assert_eq!(manager.created.load(SeqCst), 0);
+
+    let a = pool.acquire().await.unwrap();
+    assert_eq!(manager.created.load(SeqCst), 1);
+    drop(a);
+
+    // The idle value is recycled instead of creating a new one
+    let a = pool.acquire().await.unwrap();
+    assert_eq!(manager.created.load(SeqCst), 1);
+    assert_eq!(manager.recycled.load(SeqCst), 1);
+
+    let b = pool.acquire().await.unwrap();
+    assert_eq!(manager.created.load(SeqCst), 2);
+    assert_ne!(*a, *b);
+}
+
+#[cfg(feature = "time")]
+#[tokio::test]
+async fn manager_pool_waits_at_max_size() {
+    let manager = Counter::default();
+    let pool = Pool::with_manager(manager.clone(), 1);
+    let guard = pool.acquire().await.unwrap();
+    let result = pool.acquire_timeout(Duration::from_millis(10)).await;
+    assert!(matches!(result, Err(AcquireError::Timeout)));
+    assert_eq!(manager.created.load(SeqCst), 1);
+
+    drop(guard);
+    assert!(pool.acquire().await.is_ok());
+}
+
+#[tokio::test]
+async fn manager_create_error_gives_back_room() {
+    let manager = Counter::default();
+    manager.failing_creates.store(1, SeqCst);
+    let pool = Pool::with_manager(manager.clone(), 1);
+
+    assert!(matches!(pool.acquire().await, Err(AcquireError::Create(_))));
+    assert!(pool.acquire().await.is_ok());
+}
+
+#[tokio::test]
+async fn manager_recycle_failure_destroys_value() {
+    let manager = Counter::default();
+    let pool = Pool::with_manager(manager.clone(), 1);
+    drop(pool.acquire().await.unwrap());
+
+    // Value 0 is now broken, so it is detached and replaced by value 1
+    manager.broken_below.store(1, SeqCst);
+    let value = pool.acquire().await.unwrap();
+    assert_eq!(*value, 1);
+    assert_eq!(manager.detached.load(SeqCst), 1);
+}
+
+#[tokio::test]
+async fn manager_detaches_idle_values_when_pool_is_dropped() {
+    let manager = Counter::default();
+    let pool = Pool::with_manager(manager.clone(), 2);
+    let a = pool.acquire().await.unwrap();
+    let b = pool.acquire().await.unwrap();
+    drop((a, b));
+    drop(pool);
+    assert_eq!(manager.detached.load(SeqCst), 2);
+}
+
+#[tokio::test]
+async fn manager_pool_with_no_room_is_exhausted() {
+    let pool = Pool::with_manager(Counter::default(), 0);
+    assert!(matches!(pool.acquire().await, Err(AcquireError::Exhausted)));
+}
+
+#[tokio::test]
+async fn max_size_lets_initializer_pool_grow() {
+    let pool = Pool::builder().max_size(2).build_with_initializer(1, || 7);
+    assert_eq!(pool.remaining_capacity(), 1);
+    let a = pool.acquire().await.unwrap();
+    let b = pool.acquire().await.unwrap();
+    assert_eq!((*a, *b), (7, 7));
+    drop((a, b));
+    assert_eq!(pool.remaining_capacity(), 2);
+}
+
+#[tokio::test]
+async fn huge_max_size_is_not_allocated_up_front() {
+    let pool = Pool::builder()
+        .max_size(1 << 40)
+        .build_with_initializer(1, || 7);
+    let guards = pool.acquire_many(100).await.unwrap();
+    assert_eq!(pool.status().size, 100);
+    drop(guards);
+    assert_eq!(pool.remaining_capacity(), 100);
+
+    let pool = Pool::with_manager(Counter::default(), 1 << 40);
+    assert_eq!(*pool.acquire().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn maintain_destroys_idle_values() {
+    let pool = Pool::builder()
+        .idle_timeout(Duration::from_millis(10))
+        .build(0..4);
+    let guard = pool.acquire().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    // The value in use is not idle, so it survives
+    pool.maintain().await;
+    assert_eq!(pool.remaining_capacity(), 0);
+    drop(guard);
+    assert_eq!(pool.remaining_capacity(), 1);
+}
+
+#[tokio::test]
+async fn maintain_keeps_fresh_values() {
+    let pool = Pool::builder()
+        .idle_timeout(Duration::from_secs(60))
+        .build(0..4);
+    pool.maintain().await;
+    assert_eq!(pool.remaining_capacity(), 4);
+}
+
+#[tokio::test]
+async fn maintain_refills_to_min_idle() {
+    let manager = Counter::default();
+    let pool = Pool::builder()
+        .min_idle(2)
+        .build_with_manager(manager.clone(), 4);
+    pool.maintain().await;
+    assert_eq!(pool.remaining_capacity(), 2);
+    assert_eq!(manager.created.load(SeqCst), 2);
+
+    // Values in use don't count as idle
+    let _a = pool.acquire().await.unwrap();
+    pool.maintain().await;
+    assert_eq!(pool.remaining_capacity(), 2);
+    assert_eq!(manager.created.load(SeqCst), 3);
+}
+
+#[tokio::test]
+async fn kept_values_stay_available_while_expired_ones_are_destroyed() {
+    let handle: Arc<std::sync::Mutex<Option<Pool<u32>>>> = Arc::default();
+    let seen = Arc::new(AtomicUsize::new(usize::MAX));
+    let pool = Pool::builder()
+        .idle_timeout(Duration::from_millis(20))
+        .on_destroy({
+            let handle = handle.clone();
+            let seen = seen.clone();
+            move |_, _| {
+                if let Some(pool) = &*handle.lock().unwrap() {
+                    seen.store(pool.remaining_capacity(), SeqCst);
+                }
+            }
+        })
+        .build(vec![1, 2]);
+    *handle.lock().unwrap() = Some(pool.clone());
+
+    // Only the value returned just now is fresh enough to keep
+    let fresh = pool.acquire().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    drop(fresh);
+    pool.maintain().await;
+
+    assert_eq!(seen.load(SeqCst), 1);
+    assert_eq!(pool.remaining_capacity(), 1);
+    handle.lock().unwrap().take();
+}
+
+#[tokio::test]
+async fn maintain_replaces_reaped_values() {
+    let manager = Counter::default();
+    let pool = Pool::builder()
+        .min_idle(1)
+        .idle_timeout(Duration::from_millis(10))
+        .build_with_manager(manager.clone(), 4);
+    pool.maintain().await;
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    pool.maintain().await;
+    assert_eq!(pool.remaining_capacity(), 1);
+    assert_eq!(manager.created.load(SeqCst), 2);
+    assert_eq!(manager.detached.load(SeqCst), 1);
+}
+
+#[cfg(feature = "rt")]
+#[tokio::test]
+async fn spawn_maintenance_reaps_in_the_background() {
+    let pool = Pool::builder()
+        .idle_timeout(Duration::from_millis(10))
+        .build(0..4);
+    let task = pool.spawn_maintenance(Duration::from_millis(5));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(pool.remaining_capacity(), 0);
+
+    // The task stops once the pool is gone
+    drop(pool);
+    tokio::time::timeout(Duration::from_secs(1), task)
+        .await
+        .unwrap()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn expired_guard_is_destroyed_and_replaced() {
+    let manager = Counter::default();
+    let pool = Pool::builder()
+        .max_lifetime(Duration::from_millis(10))
+        .build_with_manager(manager.clone(), 1);
+    let guard = pool.acquire().await.unwrap();
+    assert_eq!(*guard, 0);
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    drop(guard);
+    assert_eq!(pool.remaining_capacity(), 0);
+    assert_eq!(manager.detached.load(SeqCst), 1);
+
+    // A replacement is created on the next acquire
+    let guard = pool.acquire().await.unwrap();
+    assert_eq!(*guard, 1);
+}
+
+#[tokio::test]
+async fn expired_idle_value_is_skipped() {
+    let pool = Pool::builder()
+        .max_lifetime(Duration::from_millis(10))
+        .build_with_initializer(1, || 1);
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    // The expired value is destroyed on the way out and replaced by the initializer
+    assert!(pool.try_acquire().is_none());
+    let value = pool.acquire().await.unwrap();
+    assert_eq!(*value, 1);
+}
+
+#[tokio::test]
+async fn expired_values_without_a_source_exhaust_the_pool() {
+    let pool = Pool::builder()
+        .max_lifetime(Duration::from_millis(10))
+        .build(vec![1]);
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(matches!(pool.acquire().await, Err(AcquireError::Exhausted)));
+}
+
+#[tokio::test]
+async fn unexpired_guard_is_returned() {
+    let pool = Pool::builder()
+        .max_lifetime(Duration::from_secs(60))
+        .build(vec![1]);
+    drop(pool.acquire().await.unwrap());
+    assert_eq!(pool.remaining_capacity(), 1);
+}
+
+#[tokio::test]
+async fn failed_acquire_check_moves_on_to_next_value() {
+    let pool = Pool::builder()
+        .test_on_acquire(|value: &mut u32| *value % 2 == 1)
+        .build(vec![0, 1, 2, 3]);
+    assert_eq!(*pool.acquire().await.unwrap(), 1);
+    assert_eq!(*pool.acquire().await.unwrap(), 3);
+    assert_eq!(pool.status().failed_checks, 2);
+    assert_eq!(pool.status().size, 2);
+}
+
+#[tokio::test]
+async fn failed_async_acquire_check_moves_on_to_next_value() {
+    let pool = Pool::builder()
+        .test_on_acquire_async(|value: &mut u32| {
+            Box::pin(async move {
+                tokio::task::yield_now().await;
+                *value != 0
+            })
+        })
+        .build(vec![0, 1]);
+    assert_eq!(*pool.acquire().await.unwrap(), 1);
+    assert_eq!(pool.status().failed_checks, 1);
+}
+
+#[test]
+fn try_acquire_runs_sync_acquire_check() {
+    let pool = Pool::builder()
+        .test_on_acquire(|value: &mut u32| *value != 0)
+        .build(vec![0, 1]);
+    assert_eq!(*pool.try_acquire().unwrap(), 1);
+    assert_eq!(pool.status().failed_checks, 1);
+}
+
+#[tokio::test]
+async fn try_acquire_skips_values_that_need_awaiting() {
+    let pool = Pool::builder()
+        .test_on_return_async(|value: &mut u32| {
+            let value = *value;
+            Box::pin(async move {
+                if value >= 10 {
+                    tokio::task::yield_now().await;
+                }
+                true
+            })
+        })
+        .build(vec![0, 1]);
+    let mut value = pool.acquire().await.unwrap();
+    *value = 10;
+    drop(value);
+
+    // The value whose check isn't done straight away is left for `acquire`
+    let value = pool.try_acquire().unwrap();
+    assert_eq!(*value, 1);
+    assert!(pool.try_acquire().is_none());
+    assert_eq!(pool.remaining_capacity(), 1);
+    assert_eq!(pool.status().size, 2);
+    drop(value);
+    assert_eq!(pool.status().destroyed, 0);
+}
+
+#[tokio::test]
+async fn try_acquire_recycles_values_from_a_manager() {
+    let manager = Counter::default();
+    let pool = Pool::with_manager(manager.clone(), 1);
+    drop(pool.acquire().await.unwrap());
+
+    let value = pool.try_acquire().unwrap();
+    assert_eq!(*value, 0);
+    assert_eq!(manager.recycled.load(SeqCst), 1);
+    drop(value);
+
+    // A value that fails recycling is destroyed
+    manager.broken_below.store(1, SeqCst);
+    assert!(pool.try_acquire().is_none());
+    assert_eq!(pool.status().size, 0);
+    assert_eq!(manager.detached.load(SeqCst), 1);
+}
+
+#[tokio::test]
+async fn failed_return_check_destroys_value() {
+    let pool = Pool::builder()
+        .test_on_return(|value: &mut u32| *value < 10)
+        .build(vec![0, 1]);
+    let mut a = pool.acquire().await.unwrap();
+    let b = pool.acquire().await.unwrap();
+    *a = 10;
+    drop((a, b));
+    assert_eq!(pool.remaining_capacity(), 1);
+    assert_eq!(pool.status().failed_checks, 1);
+    assert_eq!(*pool.acquire().await.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn async_return_check_runs_before_next_acquire() {
+    let pool = Pool::builder()
+        .test_on_return_async(|value: &mut u32| Box::pin(async move { *value < 10 }))
+        .build(vec![0]);
+    let mut value = pool.acquire().await.unwrap();
+    *value = 10;
+    drop(value);
+
+    // The check hasn't run yet, so the value is still in the pool
+    assert_eq!(pool.remaining_capacity(), 1);
+    assert_eq!(pool.status().failed_checks, 0);
+
+    assert!(matches!(pool.acquire().await, Err(AcquireError::Exhausted)));
+    assert_eq!(pool.status().failed_checks, 1);
+}
+
+#[tokio::test]
+async fn passing_checks_keep_values() {
+    let pool = Pool::builder()
+        .test_on_acquire(|_: &mut u32| true)
+        .test_on_return(|_: &mut u32| true)
+        .build(vec![0]);
+    for _ in 0..10 {
+        drop(pool.acquire().await.unwrap());
+    }
+    assert_eq!(pool.remaining_capacity(), 1);
+    assert_eq!(pool.status().failed_checks, 0);
+}
+
+#[tokio::test]
+async fn reset_on_return_clears_collections() {
+    use std::collections::{HashMap, VecDeque};
+
+    let vecs = Pool::builder().reset_on_return().build(vec![vec![0u8; 0]]);
+    vecs.acquire().await.unwrap().push(1);
+    assert!(vecs.acquire().await.unwrap().is_empty());
+
+    let strings = Pool::builder().reset_on_return().build(vec![String::new()]);
+    strings.acquire().await.unwrap().push('a');
+    assert!(strings.acquire().await.unwrap().is_empty());
+
+    let maps = Pool::builder()
+        .reset_on_return()
+        .build(vec![HashMap::<u32, u32>::new()]);
+    maps.acquire().await.unwrap().insert(1, 1);
+    assert!(maps.acquire().await.unwrap().is_empty());
+
+    let deques = Pool::builder()
+        .reset_on_return()
+        .build(vec![VecDeque::<u32>::new()]);
+    deques.acquire().await.unwrap().push_back(1);
+    assert!(deques.acquire().await.unwrap().is_empty());
+}
+
+#[cfg(feature = "bytes")]
+#[tokio::test]
+async fn reset_on_return_clears_bytes() {
+    let pool = Pool::builder()
+        .reset_on_return()
+        .build(vec![bytes::BytesMut::with_capacity(64)]);
+    pool.acquire().await.unwrap().extend_from_slice(b"hello");
+    let buffer = pool.acquire().await.unwrap();
+    assert!(buffer.is_empty());
+    assert!(buffer.capacity() >= 64);
+}
+
+#[tokio::test]
+async fn reset_runs_before_return_check() {
+    let pool = Pool::builder()
+        .reset(|value: &mut u32| *value = 0)
+        .test_on_return(|value: &mut u32| *value == 0)
+        .build(vec![0]);
+    *pool.acquire().await.unwrap() = 5;
+    assert_eq!(pool.status().failed_checks, 0);
+    assert_eq!(*pool.acquire().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn detached_value_leaves_the_pool() {
+    let pool = Pool::from_vec(vec![1, 2]);
+    let value = Guard::detach(pool.acquire().await.unwrap());
+    assert_eq!(value, 1);
+    assert_eq!(pool.status().size, 1);
+    assert_eq!(pool.remaining_capacity(), 1);
+
+    let value = Guard::detach(pool.acquire().await.unwrap());
+    assert_eq!(value, 2);
+    assert!(matches!(pool.acquire().await, Err(AcquireError::Exhausted)));
+}
+
+#[tokio::test]
+async fn detached_value_is_replaced_by_initializer() {
+    let pool = Pool::builder().build_with_initializer(1, || 7);
+    let mut value = Guard::detach(pool.acquire().await.unwrap());
+    value += 1;
+    assert_eq!(value, 8);
+    assert_eq!(*pool.acquire().await.unwrap(), 7);
+    assert_eq!(pool.status().size, 1);
+}
+
+#[tokio::test]
+async fn detach_wakes_a_waiter_that_can_create() {
+    let manager = Counter::default();
+    let pool = Pool::with_manager(manager.clone(), 1);
+    let guard = pool.acquire().await.unwrap();
+
+    let waiter = tokio::spawn({
+        let pool = pool.clone();
+        async move { *pool.acquire().await.unwrap() }
+    });
+    tokio::task::yield_now().await;
+
+    assert_eq!(Guard::detach(guard), 0);
+    let value = tokio::time::timeout(Duration::from_secs(1), waiter)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(value, 1);
+
+    // Detached values are the caller's, so the manager never sees them again
+    assert_eq!(manager.detached.load(SeqCst), 0);
+}
+
+#[tokio::test]
+async fn add_grows_a_live_pool() {
+    let pool = Pool::from_vec(vec![1]);
+    for i in 2..=100 {
+        pool.add(i).unwrap();
+    }
+    assert_eq!(pool.status().size, 100);
+
+    let mut guards = Vec::new();
+    for _ in 0..100 {
+        guards.push(pool.acquire().await.unwrap());
+    }
+    assert_eq!(pool.remaining_capacity(), 0);
+    drop(guards);
+    assert_eq!(pool.remaining_capacity(), 100);
+}
+
+#[tokio::test]
+async fn add_wakes_a_waiter() {
+    let pool = Pool::from_vec(vec![1]);
+    let _guard = pool.acquire().await.unwrap();
+    let waiter = tokio::spawn({
+        let pool = pool.clone();
+        async move { *pool.acquire().await.unwrap() }
+    });
+    tokio::task::yield_now().await;
+
+    pool.add(2).unwrap();
+    let value = tokio::time::timeout(Duration::from_secs(1), waiter)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(value, 2);
+}
+
+#[test]
+fn add_respects_max_size() {
+    let pool = Pool::builder().max_size(2).build(vec![1]);
+    assert_eq!(pool.add(2), Ok(()));
+    assert_eq!(pool.add(3), Err(3));
+
+    let pool = Pool::from_initializer(1, || 0);
+    assert_eq!(pool.add(1), Err(1));
+}
+
+#[tokio::test]
+async fn remove_idle_leaves_values_in_use() {
+    let pool = Pool::from_vec(vec![1, 2, 3]);
+    let guard = pool.acquire().await.unwrap();
+    assert_eq!(pool.remove_idle(10), vec![2, 3]);
+    assert_eq!(pool.status().size, 1);
+    drop(guard);
+    assert_eq!(pool.remaining_capacity(), 1);
+}
+
+#[tokio::test]
+async fn removed_values_are_replaced_by_initializer() {
+    let pool = Pool::builder().build_with_initializer(2, || 5);
+    assert_eq!(pool.remove_idle(2), vec![5, 5]);
+    assert_eq!(*pool.acquire().await.unwrap(), 5);
+    assert_eq!(pool.status().size, 1);
+}
+
+#[tokio::test]
+async fn close_fails_pending_and_future_acquires() {
+    let pool = Pool::from_vec(vec![1]);
+    let _guard = pool.acquire().await.unwrap();
+    let waiters: Vec<_> = (0..3)
+        .map(|_| {
+            let pool = pool.clone();
+            tokio::spawn(async move { pool.acquire().await.map(|_| ()) })
+        })
+        .collect();
+    tokio::task::yield_now().await;
+
+    pool.close();
+    for waiter in waiters {
+        let result = tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(result, Err(AcquireError::Closed)));
+    }
+
+    assert!(matches!(pool.acquire().await, Err(AcquireError::Closed)));
+    assert!(pool.try_acquire().is_none());
+    assert_eq!(pool.add(2), Err(2));
+}
+
+#[tokio::test]
+async fn close_destroys_idle_and_returned_values() {
+    let manager = Counter::default();
+    let pool = Pool::builder()
+        .min_idle(2)
+        .build_with_manager(manager.clone(), 3);
+    pool.maintain().await;
+    let guard = pool.acquire().await.unwrap();
+    assert_eq!(pool.status().size, 2);
+
+    pool.close();
+    assert_eq!(manager.detached.load(SeqCst), 1);
+    assert_eq!(pool.status().size, 1);
+
+    drop(guard);
+    assert_eq!(manager.detached.load(SeqCst), 2);
+    assert_eq!(pool.status().size, 0);
+
+    // A closed pool is never refilled
+    pool.maintain().await;
+    assert_eq!(manager.created.load(SeqCst), 2);
+}
+
+#[tokio::test]
+async fn drain_waits_for_every_guard() {
+    let pool = Pool::from_vec(vec![1, 2, 3]);
+    let guards = vec![pool.acquire().await.unwrap(), pool.acquire().await.unwrap()];
+
+    let drain = tokio::spawn({
+        let pool = pool.clone();
+        async move { pool.drain().await }
+    });
+    tokio::task::yield_now().await;
+    assert!(!drain.is_finished());
+    assert!(matches!(pool.acquire().await, Err(AcquireError::Closed)));
+
+    for guard in guards {
+        tokio::task::yield_now().await;
+        drop(guard);
+    }
+
+    let mut values = tokio::time::timeout(Duration::from_secs(1), drain)
+        .await
+        .unwrap()
+        .unwrap();
+    values.sort();
+    assert_eq!(values, vec![1, 2, 3]);
+    assert_eq!(pool.status().size, 0);
+}
+
+#[tokio::test]
+async fn drain_skips_detached_values() {
+    let pool = Pool::from_vec(vec![1, 2]);
+    let guard = pool.acquire().await.unwrap();
+    let drain = tokio::spawn({
+        let pool = pool.clone();
+        async move { pool.drain().await }
+    });
+    tokio::task::yield_now().await;
+
+    assert_eq!(Guard::detach(guard), 1);
+    let values = tokio::time::timeout(Duration::from_secs(1), drain)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(values, vec![2]);
+}
+
+#[tokio::test]
+async fn status_counts_acquires_and_releases() {
+    let pool = Pool::from_vec(vec![1, 2, 3]);
+    let first = pool.acquire().await.unwrap();
+    let second = pool.try_acquire().unwrap();
+
+    let status = pool.status();
+    assert_eq!((status.size, status.idle, status.in_use), (3, 1, 2));
+    assert_eq!((status.acquires, status.releases), (2, 0));
+
+    drop(first);
+    Guard::detach(second);
+
+    let status = pool.status();
+    assert_eq!((status.size, status.idle, status.in_use), (2, 2, 0));
+    assert_eq!((status.acquires, status.releases), (2, 2));
+}
+
+#[cfg(feature = "time")]
+#[tokio::test]
+async fn status_counts_waiters_and_timeouts() {
+    let pool = Pool::from_vec(vec![1]);
+    let guard = pool.acquire().await.unwrap();
+
+    let waiter = tokio::spawn({
+        let pool = pool.clone();
+        async move {
+            pool.acquire_timeout(Duration::from_millis(10))
+                .await
+                .is_ok()
+        }
+    });
+    tokio::task::yield_now().await;
+    assert_eq!(pool.status().waiters, 1);
+
+    assert!(!waiter.await.unwrap());
+    let status = pool.status();
+    assert_eq!((status.waiters, status.timeouts), (0, 1));
+    drop(guard);
+}
+
+#[tokio::test]
+async fn status_counts_created_and_destroyed() {
+    let manager = Counter::default();
+    let pool = Pool::builder()
+        .test_on_return(|value: &mut usize| *value != 0)
+        .build_with_manager(manager, 2);
+
+    let first = pool.acquire().await.unwrap();
+    let second = pool.acquire().await.unwrap();
+    drop(first);
+    drop(second);
+
+    let status = pool.status();
+    assert_eq!((status.created, status.destroyed), (2, 1));
+    assert_eq!((status.size, status.failed_checks), (1, 1));
+}
+
+#[test]
+fn metrics_start_empty() {
+    let pool = Pool::from_vec(vec![1]);
+    let metrics = pool.metrics();
+    assert_eq!(metrics.wait, Percentiles::default());
+    assert_eq!(metrics.hold, Percentiles::default());
+}
+
+#[tokio::test]
+async fn metrics_record_wait_and_hold_times() {
+    let pool = Pool::builder().record_timings(true).build(vec![1]);
+    let guard = pool.acquire().await.unwrap();
+
+    let waiter = tokio::spawn({
+        let pool = pool.clone();
+        async move { drop(pool.acquire().await.unwrap()) }
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    drop(guard);
+    waiter.await.unwrap();
+
+    let metrics = pool.metrics();
+    assert_eq!(metrics.wait.count, 2);
+    assert_eq!(metrics.hold.count, 2);
+    assert!(metrics.wait.max >= Duration::from_millis(20));
+    assert!(metrics.hold.max >= Duration::from_millis(20));
+
+    // The first acquire didn't wait, so the median is the short one
+    assert!(metrics.wait.p50 < Duration::from_millis(20));
+    assert_eq!(metrics.wait.p99, metrics.wait.max);
+}
+
+#[tokio::test]
+async fn metrics_are_only_recorded_when_asked() {
+    let pool = Pool::from_vec(vec![1]);
+    drop(pool.acquire().await.unwrap());
+    let metrics = pool.metrics();
+    assert_eq!(metrics.wait, Percentiles::default());
+    assert_eq!(metrics.hold, Percentiles::default());
+}
+
+#[tokio::test]
+async fn metrics_percentiles_are_ordered() {
+    let pool = Pool::builder().record_timings(true).build(vec![1]);
+    for i in 0..100 {
+        let guard = pool.acquire().await.unwrap();
+        if i % 10 == 0 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        drop(guard);
+    }
+
+    let hold = pool.metrics().hold;
+    assert_eq!(hold.count, 100);
+    assert!(hold.p50 <= hold.p90 && hold.p90 <= hold.p99 && hold.p99 <= hold.max);
+    assert!(hold.p50 < Duration::from_millis(1));
+    assert!(hold.p99 >= Duration::from_millis(1));
+}
+
+/// A `tracing` subscriber that records the pool and message of every event,
+/// and the name of every span
+#[cfg(feature = "tracing")]
+#[derive(Clone, Default)]
+struct Recorder {
+    events: Arc<std::sync::Mutex<Vec<(String, String)>>>,
+    spans: Arc<std::sync::Mutex<Vec<String>>>,
+}
+
+#[cfg(feature = "tracing")]
+impl Recorder {
+    fn messages(&self) -> Vec<String> {
+        let events = self.events.lock().unwrap();
+        assert!(events.iter().all(|(pool, _)| pool == "test"));
+        events.iter().map(|(_, message)| message.clone()).collect()
+    }
+}
+
+#[cfg(feature = "tracing")]
+#[derive(Default)]
+struct Fields {
+    pool: String,
+    message: String,
+}
+
+#[cfg(feature = "tracing")]
+impl tracing::field::Visit for Fields {
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        if field.name() == "pool" {
+            self.pool = value.to_string();
+        }
+    }
+
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{value:?}");
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl tracing::Subscriber for Recorder {
+    fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+        let mut spans = self.spans.lock().unwrap();
+        spans.push(span.metadata().name().to_string());
+        tracing::span::Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+
+    fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+    fn event(&self, event: &tracing::Event<'_>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        self.events
+            .lock()
+            .unwrap()
+            .push((fields.pool, fields.message));
+    }
+
+    fn enter(&self, _: &tracing::span::Id) {}
+
+    fn exit(&self, _: &tracing::span::Id) {}
+}
+
+#[cfg(feature = "tracing")]
+#[tokio::test]
+async fn tracing_events_carry_the_pool_name() {
+    let recorder = Recorder::default();
+    let _default = tracing::subscriber::set_default(recorder.clone());
+
+    let pool = Pool::builder()
+        .name("test")
+        .slow_acquire_threshold(Duration::from_millis(1))
+        .test_on_return(|value: &mut usize| *value != 0)
+        .build_with_manager(Counter::default(), 1);
+    let guard = pool.acquire().await.unwrap();
+
+    let waiter = tokio::spawn({
+        let pool = pool.clone();
+        async move { drop(pool.acquire().await.unwrap()) }
+    });
+    tokio::task::yield_now().await;
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    drop(guard);
+    waiter.await.unwrap();
+    drop(pool.acquire().await.unwrap());
+
+    assert_eq!(
+        recorder.messages(),
+        [
+            "created a pooled value",
+            "waiting for a pooled value",
+            "destroyed a pooled value",
+            "created a pooled value",
+            "slow acquire from the pool",
+            "recycled a pooled value",
+        ]
+    );
+    assert_eq!(*recorder.spans.lock().unwrap(), ["wait"]);
+}
+
+/// A `metrics` recorder that keeps the latest value of every metric, by name and `pool` label
+#[cfg(feature = "metrics")]
+#[derive(Default)]
+struct Registry {
+    metrics: std::sync::Mutex<std::collections::HashMap<(String, String), Arc<AtomicU64>>>,
+}
+
+#[cfg(feature = "metrics")]
+impl Registry {
+    fn register(&self, key: &metrics::Key) -> Arc<AtomicU64> {
+        let pool = key
+            .labels()
+            .find(|label| label.key() == "pool")
+            .map(|label| label.value().to_string())
+            .unwrap_or_default();
+        let mut metrics = self.metrics.lock().unwrap();
+        let entry = metrics.entry((key.name().to_string(), pool));
+        entry.or_default().clone()
+    }
+
+    fn counter(&self, name: &str, pool: &str) -> u64 {
+        let metrics = self.metrics.lock().unwrap();
+        metrics[&(name.to_string(), pool.to_string())].load(SeqCst)
+    }
+
+    fn gauge(&self, name: &str, pool: &str) -> f64 {
+        f64::from_bits(self.counter(name, pool))
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl metrics::Recorder for Registry {
+    fn describe_counter(
+        &self,
+        _: metrics::KeyName,
+        _: Option<metrics::Unit>,
+        _: metrics::SharedString,
+    ) {
+    }
+
+    fn describe_gauge(
+        &self,
+        _: metrics::KeyName,
+        _: Option<metrics::Unit>,
+        _: metrics::SharedString,
+    ) {
+    }
+
+    fn describe_histogram(
+        &self,
+        _: metrics::KeyName,
+        _: Option<metrics::Unit>,
+        _: metrics::SharedString,
+    ) {
+    }
+
+    fn register_counter(&self, key: &metrics::Key, _: &metrics::Metadata<'_>) -> metrics::Counter {
+        metrics::Counter::from_arc(self.register(key))
+    }
+
+    fn register_gauge(&self, key: &metrics::Key, _: &metrics::Metadata<'_>) -> metrics::Gauge {
+        metrics::Gauge::from_arc(self.register(key))
+    }
+
+    fn register_histogram(
+        &self,
+        _: &metrics::Key,
+        _: &metrics::Metadata<'_>,
+    ) -> metrics::Histogram {
+        metrics::Histogram::noop()
+    }
+}
+
+#[cfg(feature = "metrics")]
+#[tokio::test]
+async fn metrics_are_published_by_pool_name() {
+    let registry = Registry::default();
+    let (first, second) = metrics::with_local_recorder(&registry, || {
+        let first = Pool::builder().name("first").build(vec![1, 2]);
+        let manager = Counter::default();
+        manager.failing_creates.store(1, SeqCst);
+        let second = Pool::builder()
+            .name("second")
+            .build_with_manager(manager, 1);
+        (first, second)
+    });
+
+    let guard = first.acquire().await.unwrap();
+    assert_eq!(registry.gauge("tub_pool_idle", "first"), 1.0);
+    assert_eq!(registry.gauge("tub_pool_in_use", "first"), 1.0);
+    assert_eq!(registry.counter("tub_pool_acquires_total", "first"), 1);
+
+    let _other = first.acquire().await.unwrap();
+    // A blocking waiter times out without the `time` feature
+    let waiter = tokio::task::spawn_blocking({
+        let first = first.clone();
+        move || {
+            first
+                .acquire_blocking_timeout(Duration::from_millis(100))
+                .is_ok()
+        }
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert_eq!(registry.gauge("tub_pool_waiters", "first"), 1.0);
+    assert!(!waiter.await.unwrap());
+    assert_eq!(registry.gauge("tub_pool_waiters", "first"), 0.0);
+    assert_eq!(registry.counter("tub_pool_timeouts_total", "first"), 1);
+
+    drop(guard);
+    assert_eq!(registry.gauge("tub_pool_idle", "first"), 1.0);
+    assert_eq!(registry.gauge("tub_pool_in_use", "first"), 1.0);
+
+    assert!(second.acquire().await.is_err());
+    assert_eq!(
+        registry.counter("tub_pool_create_failures_total", "second"),
+        1
+    );
+    assert_eq!(
+        registry.counter("tub_pool_create_failures_total", "first"),
+        0
+    );
+}
+
+#[cfg(feature = "metrics")]
+#[tokio::test]
+async fn metrics_forget_the_idle_values_of_a_dropped_pool() {
+    let registry = Registry::default();
+    let (first, second) = metrics::with_local_recorder(&registry, || {
+        let first = Pool::builder().name("shared").build(vec![1, 2]);
+        let second = Pool::builder().name("shared").build(vec![3]);
+        (first, second)
+    });
+    assert_eq!(registry.gauge("tub_pool_idle", "shared"), 3.0);
+
+    let guard = first.acquire().await.unwrap();
+    drop(first);
+    assert_eq!(registry.gauge("tub_pool_idle", "shared"), 2.0);
+
+    // The pool lives on until its last value is back
+    drop(guard);
+    assert_eq!(registry.gauge("tub_pool_idle", "shared"), 1.0);
+    drop(second);
+    assert_eq!(registry.gauge("tub_pool_idle", "shared"), 0.0);
+}
+
+#[tokio::test]
+async fn hooks_follow_a_value_through_the_pool() {
+    let log = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let hook = |name: &'static str| {
+        let log = log.clone();
+        move |value: &usize, metadata: &Metadata| {
+            log.lock().unwrap().push((name, *value, metadata.id));
+        }
+    };
+    let pool = Pool::builder()
+        .max_lifetime(Duration::from_millis(20))
+        .on_create(hook("create"))
+        .on_acquire(hook("acquire"))
+        .on_release(hook("release"))
+        .on_destroy(hook("destroy"))
+        .build_with_manager(Counter::default(), 1);
+
+    drop(pool.acquire().await.unwrap());
+    let guard = pool.acquire().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    drop(guard);
+    drop(pool.acquire().await.unwrap());
+
+    assert_eq!(
+        *log.lock().unwrap(),
+        [
+            ("create", 0, 0),
+            ("acquire", 0, 0),
+            ("release", 0, 0),
+            ("acquire", 0, 0),
+            ("release", 0, 0),
+            ("destroy", 0, 0),
+            ("create", 1, 1),
+            ("acquire", 1, 1),
+            ("release", 1, 1),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn hooks_get_wait_and_hold_times() {
+    let times = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let record = || {
+        let times = times.clone();
+        move |_: &u32, metadata: &Metadata| {
+            times.lock().unwrap().push((metadata.waited, metadata.held));
+        }
+    };
+    let pool = Pool::builder()
+        .on_acquire(record())
+        .on_release(record())
+        .build(vec![1]);
+
+    let guard = pool.acquire().await.unwrap();
+    let waiter = tokio::spawn({
+        let pool = pool.clone();
+        async move { drop(pool.acquire().await.unwrap()) }
+    });
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    drop(guard);
+    waiter.await.unwrap();
+
+    let times = times.lock().unwrap();
+    assert_eq!(times.len(), 4);
+    assert!(times[0].0.unwrap() < Duration::from_millis(10) && times[0].1.is_none());
+    assert!(times[1].0.is_none() && times[1].1.unwrap() >= Duration::from_millis(10));
+    assert!(times[2].0.unwrap() >= Duration::from_millis(10) && times[2].1.is_none());
+    assert!(times[3].0.is_none() && times[3].1.is_some());
+}
+
+#[test]
+fn initial_values_get_distinct_ids() {
+    let ids = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let pool = Pool::builder()
+        .on_create({
+            let ids = ids.clone();
+            move |_: &u32, metadata| ids.lock().unwrap().push(metadata.id)
+        })
+        .max_size(3)
+        .build_with_initializer(2, || 0);
+    assert_eq!(*ids.lock().unwrap(), [0, 1]);
+
+    // Added values are not created by the pool, but still take up an id
+    pool.add(5).unwrap();
+    let guards: Vec<_> = (0..3).map(|_| pool.try_acquire().unwrap()).collect();
+    drop(guards);
+    assert_eq!(*ids.lock().unwrap(), [0, 1]);
+}
+
+/// Run `tasks` tasks that each acquire a value `rounds` times, and return the most
+/// times any acquire was overtaken by one that started later
+async fn most_overtaken(pool: Pool<u32>, tasks: usize, rounds: usize) -> usize {
+    let arrivals = Arc::new(AtomicUsize::new(0));
+    let served = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let handles: Vec<_> = (0..tasks)
+        .map(|_| {
+            let pool = pool.clone();
+            let arrivals = arrivals.clone();
+            let served = served.clone();
+            tokio::spawn(async move {
+                for _ in 0..rounds {
+                    let ticket = arrivals.fetch_add(1, SeqCst);
+                    let guard = pool.acquire().await.unwrap();
+                    served.lock().unwrap().push(ticket);
+                    tokio::task::yield_now().await;
+                    drop(guard);
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.await.unwrap();
+    }
+
+    let served = served.lock().unwrap();
+    (0..served.len())
+        .map(|i| served[..i].iter().filter(|&&t| t > served[i]).count())
+        .max()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn returned_values_are_not_overtaken() {
+    // Returned values are handed to waiters, so even an unfair pool serves them in order
+    let pool = Pool::from_vec(vec![0]);
+    assert_eq!(most_overtaken(pool, 4, 25).await, 0);
+}
+
+#[tokio::test]
+async fn returned_value_is_handed_to_the_waiter() {
+    let pool = Pool::from_vec(vec![1]);
+    let guard = pool.acquire().await.unwrap();
+
+    let waiter = tokio::spawn({
+        let pool = pool.clone();
+        async move { *pool.acquire().await.unwrap() }
+    });
+    tokio::task::yield_now().await;
+
+    // The waiter owns the value before it even runs, so newcomers can't take it
+    drop(guard);
+    assert!(pool.try_acquire().is_none());
+    assert_eq!(pool.status().idle, 0);
+    assert_eq!(waiter.await.unwrap(), 1);
+    assert!(pool.try_acquire().is_some());
+}
+
+#[tokio::test]
+async fn cancelled_waiter_passes_on_a_handed_value() {
+    let pool = Pool::from_vec(vec![1]);
+    let guard = pool.acquire().await.unwrap();
+
+    let first = tokio::spawn({
+        let pool = pool.clone();
+        async move { pool.acquire().await.map(|_| ()) }
+    });
+    let second = tokio::spawn({
+        let pool = pool.clone();
+        async move { *pool.acquire().await.unwrap() }
+    });
+    tokio::task::yield_now().await;
+
+    // The value is handed to the first waiter, which is cancelled before taking it
+    drop(guard);
+    first.abort();
+    assert!(first.await.unwrap_err().is_cancelled());
+    assert_eq!(second.await.unwrap(), 1);
+
+    // With no one left waiting the value goes back into the queue
+    let guard = pool.acquire().await.unwrap();
+    let waiter = tokio::spawn({
+        let pool = pool.clone();
+        async move { pool.acquire().await.map(|_| ()) }
+    });
+    tokio::task::yield_now().await;
+    drop(guard);
+    waiter.abort();
+    let _ = waiter.await;
+    assert_eq!(pool.status().idle, 1);
+}
+
+#[tokio::test]
+async fn fair_pool_never_lets_new_arrivals_overtake() {
+    let pool = Pool::builder().fair(true).build(vec![0]);
+    assert_eq!(most_overtaken(pool, 4, 25).await, 0);
+
+    let pool = Pool::builder().fair(true).build(vec![0, 0, 0]);
+    assert_eq!(most_overtaken(pool, 16, 25).await, 0);
+}
+
+#[tokio::test]
+async fn fair_pool_serves_waiters_in_arrival_order() {
+    let pool = Pool::builder().fair(true).build_with_initializer(1, || 0);
+    let guard = pool.acquire().await.unwrap();
+
+    let order = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let waiters: Vec<_> = (0..10)
+        .map(|i| {
+            let pool = pool.clone();
+            let order = order.clone();
+            tokio::spawn(async move {
+                let _guard = pool.acquire().await.unwrap();
+                order.lock().unwrap().push(i);
+            })
+        })
+        .collect();
+    tokio::task::yield_now().await;
+
+    drop(guard);
+    for waiter in waiters {
+        waiter.await.unwrap();
+    }
+    assert_eq!(*order.lock().unwrap(), (0..10).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn fair_pool_passes_the_turn_on_when_a_waiter_leaves() {
+    let pool = Pool::builder().fair(true).build(vec![1]);
+    let guard = pool.acquire().await.unwrap();
+
+    // The first waiter gives up, which must not strand the second
+    let first = tokio::spawn({
+        let pool = pool.clone();
+        async move { pool.acquire().await.map(|_| ()) }
+    });
+    let second = tokio::spawn({
+        let pool = pool.clone();
+        async move { *pool.acquire().await.unwrap() }
+    });
+    tokio::task::yield_now().await;
+
+    first.abort();
+    drop(guard);
+    let value = tokio::time::timeout(Duration::from_secs(1), second)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(value, 1);
+}
+
+/// Start a task per priority, in order, all waiting on a pool with its only value in
+/// use, and return the order they were served in
+async fn serve_order(pool: Pool<u32>, priorities: &[Priority]) -> Vec<usize> {
+    let guard = pool.acquire().await.unwrap();
+    let order = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let waiters: Vec<_> = priorities
+        .iter()
+        .enumerate()
+        .map(|(i, &priority)| {
+            let pool = pool.clone();
+            let order = order.clone();
+            tokio::spawn(async move {
+                let _guard = pool.acquire_with_priority(priority).await.unwrap();
+                order.lock().unwrap().push(i);
+            })
+        })
+        .collect();
+    tokio::task::yield_now().await;
+
+    drop(guard);
+    for waiter in waiters {
+        waiter.await.unwrap();
+    }
+    let order = order.lock().unwrap();
+    order.clone()
+}
+
+#[tokio::test]
+async fn waiters_are_served_by_priority() {
+    use Priority::{High, Low, Normal};
+    let priorities = [Low, Normal, High, Normal, High, Low];
+
+    let pool = Pool::from_vec(vec![0]);
+    assert_eq!(serve_order(pool, &priorities).await, [2, 4, 1, 3, 0, 5]);
+
+    let pool = Pool::builder().fair(true).build(vec![0]);
+    assert_eq!(serve_order(pool, &priorities).await, [2, 4, 1, 3, 0, 5]);
+}
+
+#[tokio::test]
+async fn waiters_are_served_in_arrival_order_without_aging() {
+    use Priority::{High, Low, Normal};
+    let pool = Pool::builder()
+        .priority_aging(Duration::ZERO)
+        .build(vec![0]);
+    assert_eq!(serve_order(pool, &[Low, Normal, High]).await, [0, 1, 2]);
+}
+
+#[tokio::test]
+async fn low_priority_waiter_is_not_starved() {
+    let pool = Pool::builder()
+        .priority_aging(Duration::from_millis(20))
+        .build(vec![0]);
+    let guard = pool.acquire().await.unwrap();
+    let order = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let spawn = |priority| {
+        let pool = pool.clone();
+        let order = order.clone();
+        tokio::spawn(async move {
+            let _guard = pool.acquire_with_priority(priority).await.unwrap();
+            order.lock().unwrap().push(priority);
+        })
+    };
+
+    // Two priorities below the high priority waiter, the low priority waiter catches
+    // up after twice the aging interval and is served first
+    let low = spawn(Priority::Low);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let high = spawn(Priority::High);
+    tokio::task::yield_now().await;
+
+    drop(guard);
+    low.await.unwrap();
+    high.await.unwrap();
+    assert_eq!(*order.lock().unwrap(), [Priority::Low, Priority::High]);
+}
+
+#[cfg(feature = "time")]
+#[tokio::test]
+async fn acquire_with_priority_honors_the_wait_timeout() {
+    let pool = Pool::builder()
+        .wait_timeout(Duration::from_millis(10))
+        .build(vec![0]);
+    let _guard = pool.acquire().await.unwrap();
+    let result = pool.acquire_with_priority(Priority::High).await;
+    assert!(matches!(result, Err(AcquireError::Timeout)));
+}
+
+/// Counts the allocations made on each thread
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // The count is gone while the thread shuts down
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn acquire_one_value_without_allocating() {
+    let pool = Pool::from_vec(vec![1]);
+    futures::executor::block_on(async {
+        // The first acquire may set up `tracing` or `metrics` callsites
+        drop(pool.acquire().await.unwrap());
+
+        let before = ALLOCATIONS.with(Cell::get);
+        drop(pool.acquire().await.unwrap());
+        assert_eq!(ALLOCATIONS.with(Cell::get), before);
+    });
+}
+
+#[tokio::test]
+async fn acquire_many_takes_values_together() {
+    let pool = Pool::from_vec(vec![1, 2, 3]);
+    let mut values = pool.acquire_many(3).await.unwrap();
+    values.sort();
+    assert_eq!(*values, [1, 2, 3]);
+    assert!(pool.try_acquire().is_none());
+    assert_eq!(pool.status().acquires, 3);
+
+    drop(values);
+    assert_eq!(pool.status().idle, 3);
+    assert_eq!(pool.status().releases, 3);
+    assert!(pool.acquire_many(0).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn acquire_many_creates_what_is_missing() {
+    let manager = Counter::default();
+    let pool = Pool::with_manager(manager.clone(), 4);
+    drop(pool.acquire().await.unwrap());
+
+    let values = pool.acquire_many(3).await.unwrap();
+    assert_eq!(values.len(), 3);
+    assert_eq!(manager.created.load(SeqCst), 3);
+    assert_eq!(manager.recycled.load(SeqCst), 1);
 }
 
-#[test]
-fn test_from_trait_2() {
-    let pool: Pool<String> = (0..100).map(|_| String::from("hello")).into();
-    assert_eq!(pool.remaining_capacity(), 100);
+#[tokio::test]
+async fn acquire_many_fails_when_the_pool_is_too_small() {
+    let pool = Pool::from_copy(2, 0);
+    assert!(matches!(
+        pool.acquire_many(3).await,
+        Err(AcquireError::Exhausted)
+    ));
+    assert_eq!(pool.status().idle, 2);
+}
+
+#[tokio::test]
+async fn acquire_many_is_not_overtaken_by_smaller_requests() {
+    let pool = Pool::from_vec(vec![1, 2]);
+    let a = pool.acquire().await.unwrap();
+    let b = pool.acquire().await.unwrap();
+
+    let many = tokio::spawn({
+        let pool = pool.clone();
+        async move { pool.acquire_many(2).await.map(|values| values.len()) }
+    });
+    tokio::task::yield_now().await;
+    let one = tokio::spawn({
+        let pool = pool.clone();
+        async move { pool.acquire().await.map(|_| ()) }
+    });
+    tokio::task::yield_now().await;
+
+    // The first value is set aside for the set, rather than going to the single waiter
+    drop(a);
+    tokio::task::yield_now().await;
+    assert!(!many.is_finished());
+    assert!(!one.is_finished());
+    assert!(pool.try_acquire().is_none());
+
+    drop(b);
+    assert_eq!(many.await.unwrap().unwrap(), 2);
+    one.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn cancelled_acquire_many_gives_back_what_it_was_handed() {
+    let pool = Pool::from_vec(vec![1, 2]);
+    let a = pool.acquire().await.unwrap();
+    let b = pool.acquire().await.unwrap();
+
+    let many = tokio::spawn({
+        let pool = pool.clone();
+        async move { pool.acquire_many(2).await.map(|_| ()) }
+    });
+    tokio::task::yield_now().await;
+
+    drop(a);
+    many.abort();
+    assert!(many.await.unwrap_err().is_cancelled());
+    assert_eq!(pool.status().idle, 1);
+    drop(b);
+    assert_eq!(pool.status().idle, 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn acquire_many_does_not_deadlock() {
+    let pool = Pool::from_vec(vec![0; 4]);
+    let handles: Vec<_> = (0..16)
+        .map(|i| {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                for _ in 0..100 {
+                    let _values = pool.acquire_many(2 + i % 3).await.unwrap();
+                    tokio::task::yield_now().await;
+                }
+            })
+        })
+        .collect();
+
+    let all = futures::future::join_all(handles);
+    let results = tokio::time::timeout(Duration::from_secs(10), all)
+        .await
+        .expect("acquire_many deadlocked");
+    assert!(results.into_iter().all(|result| result.is_ok()));
+    assert_eq!(pool.status().idle, 4);
+}
+
+fn keyed(manager: &Counter, per_key: usize, max_size: usize) -> KeyedPool<&'static str, usize> {
+    let manager = manager.clone();
+    KeyedPool::with_manager(move |_: &&str| manager.clone(), per_key, max_size)
+}
+
+#[tokio::test]
+async fn keyed_pool_keeps_values_apart_by_key() {
+    let manager = Counter::default();
+    let pool = keyed(&manager, 2, 10);
+    let a = pool.acquire(&"a").await.unwrap();
+    assert_eq!(*a, 0);
+    drop(a);
+
+    // The value idle for "a" isn't handed out for "b"
+    assert_eq!(pool.pool(&"b").status().idle, 0);
+    assert_eq!(*pool.acquire(&"b").await.unwrap(), 1);
+    assert_eq!(*pool.acquire(&"a").await.unwrap(), 0);
+    assert_eq!(pool.len(), 2);
+    assert_eq!(pool.size(), 2);
+}
+
+#[tokio::test]
+async fn keyed_pool_try_acquire_only_takes_idle_values() {
+    let manager = Counter::default();
+    let pool = keyed(&manager, 2, 10);
+    assert!(pool.try_acquire(&"a").is_none());
+    assert_eq!(pool.len(), 0);
+
+    drop(pool.acquire(&"a").await.unwrap());
+    assert_eq!(*pool.try_acquire(&"a").unwrap(), 0);
+    assert!(pool.try_acquire(&"b").is_none());
+    assert_eq!(manager.created.load(SeqCst), 1);
+}
+
+#[tokio::test]
+async fn keyed_pool_limits_each_key() {
+    let manager = Counter::default();
+    let pool = keyed(&manager, 2, 10);
+    let _a = pool.pool(&"a").acquire_many(2).await.unwrap();
+
+    let result = tokio::time::timeout(Duration::from_millis(10), pool.acquire(&"a")).await;
+    assert!(result.is_err());
+    assert!(pool.acquire(&"b").await.is_ok());
+}
+
+#[tokio::test]
+async fn keyed_pool_limits_every_key_together() {
+    let manager = Counter::default();
+    let pool = keyed(&manager, 4, 3);
+    let a = pool.acquire(&"a").await.unwrap();
+    let _b = pool.acquire(&"b").await.unwrap();
+    let _c = pool.acquire(&"c").await.unwrap();
+
+    let d = tokio::spawn({
+        let pool = pool.clone();
+        async move { pool.acquire(&"d").await.map(|value| *value) }
+    });
+    tokio::task::yield_now().await;
+    assert!(!d.is_finished());
+
+    // The value returned for "a" is destroyed to make room for "d"
+    drop(a);
+    assert_eq!(d.await.unwrap().unwrap(), 3);
+    assert_eq!(pool.size(), 3);
+    assert_eq!(manager.detached.load(SeqCst), 1);
+}
+
+#[tokio::test]
+async fn keyed_pool_evicts_idle_values_of_other_keys() {
+    let manager = Counter::default();
+    let pool = keyed(&manager, 2, 2);
+    drop(pool.pool(&"a").acquire_many(2).await.unwrap());
+    assert_eq!(pool.pool(&"a").status().idle, 2);
+
+    let _b = pool.acquire(&"b").await.unwrap();
+    assert_eq!(pool.pool(&"a").status().idle, 1);
+    assert_eq!(manager.detached.load(SeqCst), 1);
+    assert_eq!(pool.size(), 2);
+}
+
+#[tokio::test]
+async fn keyed_pool_forgets_idle_keys() {
+    let manager = Counter::default();
+    let pool: KeyedPool<&str, usize> = Pool::builder()
+        .idle_timeout(Duration::from_millis(10))
+        .build_keyed(
+            {
+                let manager = manager.clone();
+                move |_: &&str| manager.clone()
+            },
+            2,
+            10,
+        );
+    drop(pool.acquire(&"a").await.unwrap());
+    let _b = pool.acquire(&"b").await.unwrap();
+
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    pool.maintain().await;
+    // "b" is still in use, so only "a" is forgotten
+    assert_eq!(pool.len(), 1);
+    assert_eq!(pool.size(), 1);
+}
+
+/// A manager that looks at its keyed pool when it is created and dropped
+struct Reentrant(Arc<std::sync::Mutex<Option<KeyedPool<&'static str, usize>>>>);
+
+impl Reentrant {
+    fn peek(&self) {
+        if let Some(pool) = &*self.0.lock().unwrap() {
+            black_box(pool.len());
+        }
+    }
+}
+
+impl Manager for Reentrant {
+    type Type = usize;
+    type Error = TestError;
+
+    async fn create(&self) -> Result<usize, TestError> {
+        Ok(0)
+    }
+}
+
+impl Drop for Reentrant {
+    fn drop(&mut self) {
+        self.peek();
+    }
 }
 
 #[test]
-fn test_from_trait_3() {
-    let pool: Pool<Vec<u32>> = (0..10).map(|_| vec![]).into();
-    assert_eq!(pool.remaining_capacity(), 10);
+fn keyed_pool_lets_managers_call_back_into_it() {
+    let handle: Arc<std::sync::Mutex<Option<KeyedPool<&str, usize>>>> = Arc::default();
+    let pool = KeyedPool::with_manager(
+        {
+            let handle = handle.clone();
+            move |_: &&str| {
+                let manager = Reentrant(handle.clone());
+                manager.peek();
+                manager
+            }
+        },
+        2,
+        10,
+    );
+    *handle.lock().unwrap() = Some(pool.clone());
+
+    let (done, finished) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        drop(pool.pool(&"a"));
+        // Forgetting the key drops its manager
+        futures::executor::block_on(pool.maintain());
+        done.send(pool.len()).unwrap();
+    });
+    let len = finished.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(len, 0);
+
+    let pool = handle.lock().unwrap().take();
+    drop(pool);
+}
+
+#[tokio::test]
+async fn sharded_pool_returns_values_to_their_shard() {
+    let pool = Pool::builder().build_sharded(0..8, 4);
+    let guards: Vec<_> = (0..8).map(|_| pool.try_acquire().unwrap()).collect();
+    assert!(pool.try_acquire().is_none());
+
+    drop(guards);
+    for shard in pool.shards() {
+        assert_eq!(shard.status().idle, 2);
+    }
+}
+
+#[tokio::test]
+async fn sharded_pool_steals_from_other_shards() {
+    let pool = Pool::builder().build_sharded(vec![1], 4);
+    assert_eq!(*pool.acquire().await.unwrap(), 1);
+    assert_eq!(*pool.try_acquire().unwrap(), 1);
+}
+
+#[tokio::test]
+async fn sharded_pool_waits_for_any_shard() {
+    let pool = Pool::builder().build_sharded(vec![1, 2], 2);
+    let a = pool.acquire().await.unwrap();
+    let b = pool.acquire().await.unwrap();
+
+    let waiter = tokio::spawn({
+        let pool = pool.clone();
+        async move { *pool.acquire().await.unwrap() }
+    });
+    tokio::task::yield_now().await;
+
+    // The waiter is in line at both shards, but only counted once
+    let waiters: usize = pool.shards().iter().map(|s| s.status().waiters).sum();
+    assert_eq!(waiters, 1);
+
+    let value = *b;
+    drop(b);
+    assert_eq!(waiter.await.unwrap(), value);
+
+    // The waiter gave up its place at the other shard
+    drop(a);
+    assert_eq!(pool.remaining_capacity(), 2);
+    assert!(pool
+        .shards()
+        .iter()
+        .all(|shard| shard.status().waiters == 0));
+}
+
+#[tokio::test]
+async fn sharded_pool_errors() {
+    let pool: ShardedPool<u32> = Pool::builder().build_sharded(Vec::new(), 2);
+    assert!(matches!(pool.acquire().await, Err(AcquireError::Exhausted)));
+
+    let pool = Pool::builder().build_sharded(vec![1], 2);
+    pool.close();
+    assert!(matches!(pool.acquire().await, Err(AcquireError::Closed)));
+}
+
+#[tokio::test]
+async fn sharded_pool_creates_values_in_each_shard() {
+    let manager = Counter::default();
+    let pool = Pool::builder().build_sharded_with_manager(manager.clone(), 4, 2);
+    let guards: Vec<_> = futures::future::join_all((0..4).map(|_| pool.acquire())).await;
+    assert!(guards.iter().all(Result::is_ok));
+    assert_eq!(manager.created.load(SeqCst), 4);
+    for shard in pool.shards() {
+        assert_eq!(shard.status().size, 2);
+    }
+}
+
+/// A manager that takes a while to create each value
+#[derive(Clone, Default)]
+struct Slow {
+    created: Arc<AtomicUsize>,
+}
+
+impl Manager for Slow {
+    type Type = usize;
+    type Error = TestError;
+
+    async fn create(&self) -> Result<usize, TestError> {
+        let value = self.created.fetch_add(1, SeqCst);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        Ok(value)
+    }
+}
+
+#[tokio::test]
+async fn sharded_pool_creates_one_value_per_acquire() {
+    let manager = Slow::default();
+    let pool = Pool::builder().build_sharded_with_manager(manager.clone(), 8, 4);
+    let _value = pool.acquire().await.unwrap();
+    assert_eq!(manager.created.load(SeqCst), 1);
+
+    let sizes: usize = pool.shards().iter().map(|shard| shard.status().size).sum();
+    assert_eq!(sizes, 1);
+}
+
+#[tokio::test]
+async fn sharded_pool_never_exceeds_max_size() {
+    let manager = Counter::default();
+    let pool = Pool::builder().build_sharded_with_manager(manager.clone(), 10, 4);
+    let mut guards = Vec::new();
+    while let Ok(guard) = tokio::time::timeout(Duration::from_millis(10), pool.acquire()).await {
+        guards.push(guard.unwrap());
+    }
+    assert_eq!(guards.len(), 10);
+    assert_eq!(manager.created.load(SeqCst), 10);
+
+    let sizes: Vec<_> = pool
+        .shards()
+        .iter()
+        .map(|shard| shard.status().size)
+        .collect();
+    assert_eq!(sizes, [3, 3, 2, 2]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sharded_pool_under_contention_loses_no_values() {
+    let pool = Pool::builder().build_sharded(0..8, 4);
+    let handles: Vec<_> = (0..32)
+        .map(|_| {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                for _ in 0..500 {
+                    let _guard = pool.acquire().await.unwrap();
+                    tokio::task::yield_now().await;
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.await.unwrap();
+    }
+
+    let guards: Vec<_> = (0..8).map(|_| pool.try_acquire().unwrap()).collect();
+    let mut values: Vec<_> = guards.iter().map(|guard| **guard).collect();
+    values.sort();
+    assert_eq!(values, (0..8).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn local_pool_holds_values_that_are_not_send() {
+    let pool = LocalPool::from_initializer(2, || Rc::new(RefCell::new(0)));
+    let first = pool.acquire().await.unwrap();
+    *first.borrow_mut() += 1;
+    assert_eq!(pool.remaining_capacity(), 1);
+
+    drop(first);
+    assert_eq!(pool.remaining_capacity(), 2);
+    let total: i32 = [pool.try_acquire().unwrap(), pool.try_acquire().unwrap()]
+        .iter()
+        .map(|value| *value.borrow())
+        .sum();
+    assert_eq!(total, 1);
+}
+
+#[tokio::test]
+async fn local_pool_serves_waiters_in_order() {
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(async {
+            let pool = LocalPool::from_vec(vec![0]);
+            let order = Rc::new(RefCell::new(Vec::new()));
+            let held = pool.acquire().await.unwrap();
+
+            let handles: Vec<_> = (0..4)
+                .map(|task| {
+                    let pool = pool.clone();
+                    let order = order.clone();
+                    tokio::task::spawn_local(async move {
+                        let _value = pool.acquire().await.unwrap();
+                        order.borrow_mut().push(task);
+                        tokio::task::yield_now().await;
+                    })
+                })
+                .collect();
+            tokio::task::yield_now().await;
+
+            drop(held);
+            for handle in handles {
+                handle.await.unwrap();
+            }
+            assert_eq!(*order.borrow(), [0, 1, 2, 3]);
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn local_pool_cancelled_waiter_passes_on_its_value() {
+    let pool = LocalPool::from_vec(vec![1]);
+    let held = pool.acquire().await.unwrap();
+
+    let mut first = Box::pin(pool.acquire());
+    let mut second = Box::pin(pool.acquire());
+    assert!(futures::poll!(first.as_mut()).is_pending());
+    assert!(futures::poll!(second.as_mut()).is_pending());
+
+    // The value is handed to the first waiter, which gives it to the next when cancelled
+    drop(held);
+    drop(first);
+    assert_eq!(*second.await.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn local_pool_replaces_detached_values() {
+    let pool: LocalPool<u32> = LocalPool::from_default(1);
+    let value = pool.acquire().await.unwrap();
+    let mut waiting = Box::pin(pool.acquire());
+    assert!(futures::poll!(waiting.as_mut()).is_pending());
+
+    LocalGuard::detach(value);
+    assert!(waiting.await.is_ok());
+
+    // Without an initializer, nothing can replace the last value
+    let pool = LocalPool::from_vec(vec![1]);
+    let value = pool.acquire().await.unwrap();
+    let mut waiting = Box::pin(pool.acquire());
+    assert!(futures::poll!(waiting.as_mut()).is_pending());
+
+    LocalGuard::detach(value);
+    assert!(matches!(waiting.await, Err(AcquireError::Exhausted)));
+    assert!(matches!(pool.acquire().await, Err(AcquireError::Exhausted)));
+}
+
+#[tokio::test]
+async fn local_pool_without_room_is_exhausted() {
+    // An initializer is no help if the pool may never own a value
+    let pool: LocalPool<u32> = LocalPool::from_default(0);
+    assert!(matches!(pool.acquire().await, Err(AcquireError::Exhausted)));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn blocking_acquire_waits_for_an_async_task() {
+    let pool = Pool::from_vec(vec![1]);
+    let value = pool.acquire().await.unwrap();
+
+    let waiter = std::thread::spawn({
+        let pool = pool.clone();
+        move || *pool.acquire_blocking().unwrap()
+    });
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert!(!waiter.is_finished());
+
+    drop(value);
+    assert_eq!(waiter.join().unwrap(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn async_acquire_waits_for_a_blocking_thread() {
+    let pool = Pool::from_vec(vec![1]);
+    let value = pool.acquire_blocking().unwrap();
+
+    let waiter = tokio::spawn({
+        let pool = pool.clone();
+        async move { *pool.acquire().await.unwrap() }
+    });
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(10));
+        drop(value);
+    });
+    assert_eq!(waiter.await.unwrap(), 1);
 }
 
 #[test]
-fn test_clone_a_pool() {
-    let pool = Pool::from_copy(10, 1);
-    let pool2 = pool.clone();
-    assert_eq!(pool.remaining_capacity(), 10);
-    assert_eq!(pool2.remaining_capacity(), 10);
+#[cfg(feature = "time")]
+fn blocking_acquire_times_out() {
+    let pool = Pool::builder()
+        .wait_timeout(Duration::from_millis(10))
+        .build(vec![1]);
+    let _value = pool.acquire_blocking().unwrap();
+    assert!(matches!(
+        pool.acquire_blocking(),
+        Err(AcquireError::Timeout)
+    ));
+    assert!(matches!(
+        pool.acquire_blocking_timeout(Duration::from_millis(10)),
+        Err(AcquireError::Timeout)
+    ));
+    assert_eq!(pool.status().timeouts, 2);
+    assert_eq!(pool.status().waiters, 0);
 }
 
 #[tokio::test]
-async fn guarded_value_is_mutable() {
-    let pool = Pool::from_copy(10, 1);
-    let mut box1 = pool.acquire().await;
-    assert_eq!(pool.remaining_capacity(), 9);
-    assert_eq!(*box1, 1);
-    *box1 = 2;
-    assert_eq!(*box1, 2);
+async fn fifo_pool_hands_out_the_longest_idle_value() {
+    let pool = Pool::builder()
+        .queue_mode(QueueMode::Fifo)
+        .build(vec![1, 2, 3]);
+    let first = pool.acquire().await.unwrap();
+    assert_eq!(*first, 1);
+    drop(first);
+
+    let values: Vec<_> = [
+        pool.acquire().await,
+        pool.acquire().await,
+        pool.acquire().await,
+    ]
+    .into_iter()
+    .map(|guard| *guard.unwrap())
+    .collect();
+    assert_eq!(values, [2, 3, 1]);
 }
 
 #[tokio::test]
-async fn mutated_value_is_returned_to_pool() {
-    let pool = Pool::from_copy(1, 1);
-    let mut b = pool.acquire().await;
-    assert_eq!(pool.remaining_capacity(), 0);
-    assert_eq!(*b, 1);
-    *b = 2;
-    assert_eq!(*b, 2);
+async fn lifo_pool_hands_out_the_latest_value() {
+    let pool = Pool::builder()
+        .queue_mode(QueueMode::Lifo)
+        .build(vec![1, 2, 3]);
+    let a = pool.acquire().await.unwrap();
+    let b = pool.acquire().await.unwrap();
+    assert_eq!((*a, *b), (3, 2));
+
+    drop(a);
     drop(b);
+    let values: Vec<_> = [
+        pool.acquire().await,
+        pool.acquire().await,
+        pool.acquire().await,
+    ]
+    .into_iter()
+    .map(|guard| *guard.unwrap())
+    .collect();
+    assert_eq!(values, [2, 3, 1]);
+}
+
+#[tokio::test]
+async fn lifo_pool_lets_cold_values_be_reaped() {
+    let pool = Pool::builder()
+        .queue_mode(QueueMode::Lifo)
+        .idle_timeout(Duration::from_millis(50))
+        .build(vec![1, 2, 3]);
+
+    // Only the hottest value is used, so the others go cold
+    for _ in 0..5 {
+        drop(pool.acquire().await.unwrap());
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    pool.maintain().await;
     assert_eq!(pool.remaining_capacity(), 1);
-    let b = pool.acquire().await;
-    assert_eq!(pool.remaining_capacity(), 0);
-    assert_eq!(*b, 2);
+    assert_eq!(*pool.acquire().await.unwrap(), 3);
+}
+
+#[tokio::test]
+async fn maintain_keeps_the_order_of_a_lifo_pool() {
+    let pool = Pool::builder()
+        .queue_mode(QueueMode::Lifo)
+        .idle_timeout(Duration::from_secs(60))
+        .build(vec![1, 2, 3]);
+    pool.maintain().await;
+    assert_eq!(*pool.acquire().await.unwrap(), 3);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn lifo_pool_under_contention_loses_no_values() {
+    let pool = Pool::builder().queue_mode(QueueMode::Lifo).build(0..8);
+    let handles: Vec<_> = (0..32)
+        .map(|_| {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                for _ in 0..500 {
+                    let _guard = pool.acquire().await.unwrap();
+                    tokio::task::yield_now().await;
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.await.unwrap();
+    }
+
+    let mut values = pool.drain().await;
+    values.sort();
+    assert_eq!(values, (0..8).collect::<Vec<_>>());
 }
 
 #[tokio::test]
@@ -129,7 +2316,7 @@ async fn deadlock_check_1() {
         .map(|_| {
             let pool = pool.clone();
             tokio::spawn(async move {
-                let mut b = pool.acquire().await;
+                let mut b = pool.acquire().await.unwrap();
                 *b += 1;
                 drop(b);
             })
@@ -141,7 +2328,7 @@ async fn deadlock_check_1() {
     }
 
     assert_eq!(pool.remaining_capacity(), 1);
-    let v = pool.acquire().await;
+    let v = pool.acquire().await.unwrap();
     assert_eq!(*v, 100);
 }
 
@@ -154,7 +2341,7 @@ async fn deadlock_check_2() {
         let pool = pool.clone();
         let barrier = barrier.clone();
         async move {
-            let mut b = pool.acquire().await;
+            let mut b = pool.acquire().await.unwrap();
             *b = 2;
             drop(b);
             barrier.wait().await;
@@ -166,7 +2353,7 @@ async fn deadlock_check_2() {
         let barrier = barrier.clone();
         async move {
             barrier.wait().await;
-            let mut b = pool.acquire().await;
+            let mut b = pool.acquire().await.unwrap();
             *b = 3;
         }
     });
@@ -175,7 +2362,7 @@ async fn deadlock_check_2() {
     f2.await.unwrap();
 
     assert_eq!(pool.remaining_capacity(), 1);
-    let v = pool.acquire().await;
+    let v = pool.acquire().await.unwrap();
     assert_eq!(*v, 3);
 }
 
@@ -188,7 +2375,7 @@ async fn deadlock_check_3() {
             tokio::spawn({
                 let pool = pool.clone();
                 async move {
-                    let _resource = pool.acquire().await;
+                    let _resource = pool.acquire().await.unwrap();
                 }
             })
         })
@@ -199,14 +2386,14 @@ async fn deadlock_check_3() {
             tokio::spawn({
                 let pool = pool.clone();
                 async move {
-                    let _resource = pool.acquire().await;
+                    let _resource = pool.acquire().await.unwrap();
                 }
             })
         })
         .collect();
 
     // wait for all tasks to complete
-    for handle in handles.into_iter().chain(handles2.into_iter()) {
+    for handle in handles.into_iter().chain(handles2) {
         handle.await.unwrap();
     }
 }
@@ -219,7 +2406,7 @@ async fn deadlock_check_4() {
             tokio::spawn({
                 let pool = pool.clone();
                 async move {
-                    let resource = pool.acquire().await;
+                    let resource = pool.acquire().await.unwrap();
                     // Sleep to increase the odds that other tasks are waiting for the pool.
                     tokio::time::sleep(Duration::from_nanos(1)).await;
                     black_box(resource);
@@ -241,7 +2428,7 @@ async fn deadlock_check_5() {
             tokio::spawn({
                 let pool = pool.clone();
                 async move {
-                    let resource = pool.acquire().await;
+                    let resource = pool.acquire().await.unwrap();
                     black_box(resource);
                 }
             })
@@ -253,6 +2440,37 @@ async fn deadlock_check_5() {
     }
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn deadlock_check_6() {
+    for fair in [false, true] {
+        let pool = Pool::builder().fair(fair).build(vec![0; 4]);
+        let tasks = (0..64)
+            .map(|_| {
+                let pool = pool.clone();
+                tokio::spawn(async move {
+                    for _ in 0..500 {
+                        let mut value = pool.acquire().await.unwrap();
+                        *value += 1;
+                        if *value % 7 == 0 {
+                            tokio::task::yield_now().await;
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for task in tasks {
+            tokio::time::timeout(Duration::from_secs(30), task)
+                .await
+                .unwrap()
+                .unwrap();
+        }
+
+        let total: u32 = pool.remove_idle(4).into_iter().sum();
+        assert_eq!(total, 64 * 500);
+    }
+}
+
 proptest! {
     #[test]
     fn new_from_vec_prop_property(vec in any::<Vec<u8>>()) {
@@ -277,7 +2495,7 @@ proptest! {
                 let pool = Pool::from_copy(u, 1);
                 let mut guards = Vec::new();
                 for _ in 0..u {
-                    guards.push(pool.acquire().await);
+                    guards.push(pool.acquire().await.unwrap());
                 }
                 assert_eq!(pool.remaining_capacity(), 0);
                 for guard in guards {
@@ -305,7 +2523,7 @@ proptest! {
                     tokio::spawn({
                         let pool = pool.clone();
                         async move {
-                            let resource = pool.acquire().await;
+                            let resource = pool.acquire().await.unwrap();
                             black_box(resource);
                         }
                     })
@@ -318,3 +2536,77 @@ proptest! {
         });
     }
 }
+
+#[test]
+fn futures_executor_waits_for_a_returned_value() {
+    let pool = Pool::from_vec(vec![1]);
+    let value = futures::executor::block_on(pool.acquire()).unwrap();
+
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(10));
+        drop(value);
+    });
+    let value = futures::executor::block_on(pool.acquire()).unwrap();
+    assert_eq!(*value, 1);
+}
+
+#[test]
+fn futures_executor_creates_values_with_a_manager() {
+    let manager = Counter::default();
+    let pool = Pool::with_manager(manager.clone(), 2);
+    futures::executor::block_on(async {
+        let values = pool.acquire_many(2).await.unwrap();
+        assert_eq!(manager.created.load(SeqCst), 2);
+        drop(values);
+
+        let value = pool.acquire().await.unwrap();
+        assert_eq!(manager.recycled.load(SeqCst), 1);
+        drop(value);
+        pool.maintain().await;
+    });
+    assert_eq!(pool.status().size, 2);
+}
+
+/// Let the other tasks run, without relying on a runtime
+async fn yield_once() {
+    let mut yielded = false;
+    std::future::poll_fn(|cx| {
+        if yielded {
+            return std::task::Poll::Ready(());
+        }
+        yielded = true;
+        cx.waker().wake_by_ref();
+        std::task::Poll::Pending
+    })
+    .await
+}
+
+#[test]
+fn futures_executor_serves_waiting_tasks() {
+    let mut executor = futures::executor::LocalPool::new();
+    let spawner = executor.spawner();
+    let pool = Pool::from_vec(vec![0]);
+    let local = LocalPool::from_vec(vec![0]);
+    let total = Rc::new(RefCell::new(0));
+
+    for _ in 0..8 {
+        let pool = pool.clone();
+        let local = local.clone();
+        let total = total.clone();
+        spawner
+            .spawn_local(async move {
+                let mut value = pool.acquire().await.unwrap();
+                let mut local = local.acquire().await.unwrap();
+                yield_once().await;
+                *value += 1;
+                *local += 1;
+                *total.borrow_mut() += 1;
+            })
+            .unwrap();
+    }
+    executor.run();
+
+    assert_eq!(*total.borrow(), 8);
+    assert_eq!(*pool.try_acquire().unwrap(), 8);
+    assert_eq!(*local.try_acquire().unwrap(), 8);
+}